@@ -0,0 +1,116 @@
+use anyhow::{bail, Context, Result};
+use rsf::config::{self, Profile};
+use rsf::{Auth, Client, SessionToken};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+
+fn token_path(profile: &Profile) -> Result<PathBuf> {
+    let dir = config::cache_dir().context("Cannot determine cache directory")?;
+    Ok(dir.join(format!("{}.token.json", profile.name)))
+}
+
+/// Reads the cached token of the profile.
+///
+/// A token issued by another server than the profile points at now, for example after
+/// changing `RSF_URL`, is treated as missing so it is never sent elsewhere.
+pub fn load_token(profile: &Profile) -> Result<Option<SessionToken>> {
+    let path = token_path(profile)?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    // A corrupt cache file is not worth failing over, the user just logs in again
+    let session: Option<SessionToken> = serde_json::from_str(&contents).ok();
+    Ok(session.filter(|session| session.issued_by(profile.base_url())))
+}
+
+/// Writes the token to the cache, readable only by the current user.
+pub fn save_token(profile: &Profile, session: &SessionToken) -> Result<()> {
+    let path = token_path(profile)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    // `mode` only applies to newly created files, so tighten an existing one too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(serde_json::to_string_pretty(session)?.as_bytes())?;
+    Ok(())
+}
+
+/// Removes the cached token, returning whether there was one.
+pub fn remove_token(profile: &Profile) -> Result<bool> {
+    let path = token_path(profile)?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
+
+pub fn prompt_password(username: &str) -> Result<String> {
+    if !std::io::stdin().is_terminal() {
+        bail!("Password required for {} but stdin is not a terminal", username);
+    }
+    Ok(rpassword::prompt_password(format!("Password for {}: ", username))?)
+}
+
+/// Logs in with the given credentials and caches the resulting token.
+pub async fn login(profile: &Profile, username: &str, password: &str) -> Result<SessionToken> {
+    let session = Client::new(profile, &Auth::None)?
+        .login(username, password)
+        .await
+        .context("Login failed")?;
+    save_token(profile, &session)?;
+    Ok(session)
+}
+
+/// Works out the credentials to use for the profile.
+///
+/// A cached, unexpired token is used as is. Otherwise configured credentials are exchanged
+/// for a new token; if the cached token expired and no password is configured, the user is
+/// prompted for one. Without a token or credentials requests are sent unauthenticated.
+pub async fn authenticate(profile: &Profile) -> Result<Auth> {
+    let cached = load_token(profile)?;
+    if let Some(session) = &cached {
+        if !session.is_expired() {
+            return Ok(Auth::Bearer(session.token.clone()));
+        }
+    }
+
+    let username = profile
+        .username
+        .clone()
+        .or_else(|| cached.as_ref().map(|session| session.username.clone()));
+    let Some(username) = username else {
+        return Ok(Auth::None);
+    };
+
+    let password = match &profile.password {
+        Some(password) => password.clone(),
+        None if cached.is_some() => {
+            eprintln!("Session for profile '{}' expired", profile.name);
+            prompt_password(&username)
+                .context("Session expired, run `rsf login` to log in again")?
+        }
+        None => return Ok(Auth::None),
+    };
+
+    let session = login(profile, &username, &password).await?;
+    Ok(Auth::Bearer(session.token))
+}
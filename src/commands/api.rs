@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use reqwest::Method;
+use rsf::{Client, RsfError};
+use serde_json::Value;
+use std::io::{Read, Write};
+
+use super::{settings_object, CommandFailed, Setting};
+use crate::output::cell;
+
+#[derive(Args)]
+pub struct ApiArgs {
+    /// HTTP method, e.g. GET, POST, PUT, PATCH or DELETE
+    #[arg(value_parser = parse_method)]
+    method: Method,
+    /// Endpoint path relative to the profile URL, e.g. /agent/
+    path: String,
+    /// JSON request body, or @FILE to read it from a file and @- from stdin
+    #[arg(long, short = 'd', value_name = "JSON", conflicts_with = "fields")]
+    data: Option<String>,
+    /// Body field as KEY=VALUE, sent as query parameters for GET; repeat for more fields
+    #[arg(long = "field", short = 'f', value_name = "KEY=VALUE")]
+    fields: Vec<Setting>,
+    /// Print the response headers before the body
+    #[arg(long, short)]
+    include: bool,
+}
+
+fn parse_method(method: &str) -> Result<Method> {
+    Method::from_bytes(method.to_uppercase().as_bytes())
+        .map_err(|_| anyhow!("Invalid HTTP method '{}'", method))
+}
+
+pub async fn handle_api_command(client: &Client, args: ApiArgs) -> Result<()> {
+    let mut path = format!("/{}", args.path.trim_start_matches('/'));
+    let mut body = match &args.data {
+        Some(data) => Some(read_body(data)?),
+        None => None,
+    };
+    if !args.fields.is_empty() {
+        let fields = settings_object(args.fields)?;
+        if args.method == Method::GET {
+            let pairs: Vec<(&str, String)> = fields
+                .iter()
+                .map(|(key, value)| (key.as_str(), cell(value)))
+                .collect();
+            let separator = if path.contains('?') { '&' } else { '?' };
+            path = format!("{}{}{}", path, separator, serde_urlencoded::to_string(pairs)?);
+        } else {
+            body = Some(Value::Object(fields));
+        }
+    }
+
+    let response = client.raw(args.method, &path, body.as_ref()).await?;
+
+    let mut out = std::io::stdout().lock();
+    let status_line = format!("HTTP {}", response.status);
+    if args.include {
+        writeln!(out, "{}", status_line)?;
+        for (name, value) in &response.headers {
+            writeln!(out, "{}: {}", name, String::from_utf8_lossy(value.as_bytes()))?;
+        }
+        writeln!(out)?;
+    } else {
+        // Keeps stdout to the body, so it can be piped into other tools
+        eprintln!("{}", status_line);
+    }
+    match serde_json::from_slice::<Value>(&response.body) {
+        Ok(json) => writeln!(out, "{}", serde_json::to_string_pretty(&json)?)?,
+        Err(_) => out.write_all(&response.body)?,
+    }
+    out.flush()?;
+
+    if !response.status.is_success() {
+        let code = RsfError::from_status(response.status, &path, String::new()).exit_code();
+        let message = format!("Server returned {}", response.status);
+        return Err(CommandFailed::new(code, message).into());
+    }
+    Ok(())
+}
+
+/// Reads the `--data` argument: inline JSON, `@FILE` or `@-` for stdin.
+fn read_body(data: &str) -> Result<Value> {
+    let (text, source) = match data.strip_prefix('@') {
+        Some("-") => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text).context("Failed to read stdin")?;
+            (text, "stdin".to_string())
+        }
+        Some(file) => (
+            std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file))?,
+            file.to_string(),
+        ),
+        None => (data.to_string(), "--data".to_string()),
+    };
+    serde_json::from_str(&text)
+        .with_context(|| format!("Request body in {} is not valid JSON", source))
+}
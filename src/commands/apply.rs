@@ -0,0 +1,266 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use rsf::models::Volume;
+use rsf::Client;
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+use super::confirm;
+use crate::manifest::{manifest_fields, Manifest, READ_ONLY_FIELDS};
+use crate::output::Tabular;
+
+#[derive(Args)]
+pub struct ApplyArgs {
+    /// YAML or TOML manifest with the volumes to have on the server
+    #[arg(long, short = 'f', value_name = "FILE")]
+    file: PathBuf,
+    /// Print the plan without changing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Delete volumes that are not in the manifest
+    #[arg(long)]
+    prune: bool,
+    /// Do not ask for confirmation
+    #[arg(long, short)]
+    yes: bool,
+}
+
+/// A change needed to bring the server in line with the manifest.
+enum Action {
+    Create(Box<Volume>),
+    /// Volume name and the fields to change
+    Update(String, Map<String, Value>),
+    Delete(String),
+    Unchanged(String),
+}
+
+pub async fn handle_apply_command(client: &Client, args: ApplyArgs) -> Result<()> {
+    let manifest = Manifest::load(&args.file)?;
+    let current = client.get_volumes().await?;
+    let plan = plan(manifest.volumes, &current, args.prune)?;
+
+    print_plan(&plan);
+    let unmanaged = current
+        .iter()
+        .filter(|volume| !plan.iter().any(|action| action.name() == volume.vol))
+        .count();
+    if unmanaged > 0 {
+        println!(
+            "{} volume(s) on the server are not in the manifest and left alone, use --prune \
+             to delete them",
+            unmanaged
+        );
+    }
+
+    let changes = plan
+        .iter()
+        .filter(|action| !matches!(action, Action::Unchanged(_)))
+        .count();
+    if changes == 0 || args.dry_run {
+        return Ok(());
+    }
+    if !args.yes && !confirm("Apply these changes?")? {
+        bail!("Aborted, nothing was changed");
+    }
+
+    for action in plan {
+        match action {
+            Action::Create(volume) => {
+                client.create_volume(&volume).await?;
+                println!("Created volume {}", volume.vol);
+            }
+            Action::Update(name, fields) => {
+                client.update_volume(&name, &fields).await?;
+                println!("Updated volume {}", name);
+            }
+            Action::Delete(name) => {
+                client.delete_volume(&name).await?;
+                println!("Deleted volume {}", name);
+            }
+            Action::Unchanged(_) => {}
+        }
+    }
+    Ok(())
+}
+
+impl Action {
+    fn name(&self) -> &str {
+        match self {
+            Action::Create(volume) => &volume.vol,
+            Action::Update(name, _) | Action::Delete(name) | Action::Unchanged(name) => name,
+        }
+    }
+}
+
+/// Works out what to do for each volume.
+///
+/// Only fields given in the manifest are compared, so fields left out of it keep their
+/// server values. Fields the model does not know are only accepted if the server reports
+/// them for some volume, so a misspelled field is an error rather than an update.
+fn plan(desired: Vec<Volume>, current: &[Volume], prune: bool) -> Result<Vec<Action>> {
+    for volume in &desired {
+        let unknown = volume.extra.keys().find(|key| {
+            !<Volume as Tabular>::FIELDS.contains(&key.as_str())
+                && !current.iter().any(|existing| existing.extra.contains_key(*key))
+        });
+        if let Some(key) = unknown {
+            bail!("Unknown field '{}' for volume {} in the manifest", key, volume.vol);
+        }
+    }
+
+    let mut plan = Vec::new();
+    for volume in desired {
+        let Some(existing) = current.iter().find(|existing| existing.vol == volume.vol) else {
+            plan.push(Action::Create(Box::new(volume)));
+            continue;
+        };
+
+        let Value::Object(wanted) = serde_json::to_value(&volume)? else {
+            unreachable!("volumes serialize to objects");
+        };
+        // Compared as exported, so a manifest written by `rsf export` is unchanged
+        let actual = manifest_fields(existing)?;
+        let fields: Map<String, Value> = wanted
+            .into_iter()
+            .filter(|(key, _)| key != "vol" && !READ_ONLY_FIELDS.contains(&key.as_str()))
+            .filter(|(key, value)| actual.get(key) != Some(value))
+            .collect();
+        if fields.is_empty() {
+            plan.push(Action::Unchanged(volume.vol));
+        } else {
+            plan.push(Action::Update(volume.vol, fields));
+        }
+    }
+
+    if prune {
+        for existing in current {
+            if !plan.iter().any(|action| action.name() == existing.vol) {
+                plan.push(Action::Delete(existing.vol.clone()));
+            }
+        }
+    }
+    Ok(plan)
+}
+
+fn print_plan(plan: &[Action]) {
+    let (mut creates, mut updates, mut deletes, mut unchanged) = (0, 0, 0, 0);
+    for action in plan {
+        match action {
+            Action::Create(volume) => {
+                creates += 1;
+                println!("+ create     {}", volume.vol);
+            }
+            Action::Update(name, fields) => {
+                updates += 1;
+                let keys: Vec<&str> = fields.keys().map(String::as_str).collect();
+                println!("~ update     {} ({})", name, keys.join(", "));
+            }
+            Action::Delete(name) => {
+                deletes += 1;
+                println!("- delete     {}", name);
+            }
+            Action::Unchanged(name) => {
+                unchanged += 1;
+                println!("  unchanged  {}", name);
+            }
+        }
+    }
+    println!(
+        "Plan: {} to create, {} to update, {} to delete, {} unchanged",
+        creates, updates, deletes, unchanged
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn volume(fields: Value) -> Volume {
+        serde_json::from_value(fields).unwrap()
+    }
+
+    fn server_volumes() -> Vec<Volume> {
+        vec![
+            volume(json!({
+                "vol": "proj",
+                "id": 1,
+                "type": "linux",
+                "roots": {"agent1:30002": "/mnt/proj"},
+                "ignored_dirs": [".snapshot"],
+                "scheduled_scans": [{"id": 7, "type": "diff", "schedule": "0 2 * * *"}],
+                "total_size": 1024,
+                "total_capacity": 4096,
+            })),
+            volume(json!({"vol": "home", "id": 2, "type": "linux"})),
+        ]
+    }
+
+    /// The plan written the way `apply` prints it.
+    fn summary(plan: &[Action]) -> Vec<String> {
+        plan.iter()
+            .map(|action| match action {
+                Action::Create(volume) => format!("create {}", volume.vol),
+                Action::Update(name, fields) => {
+                    let keys: Vec<&str> = fields.keys().map(String::as_str).collect();
+                    format!("update {} ({})", name, keys.join(", "))
+                }
+                Action::Delete(name) => format!("delete {}", name),
+                Action::Unchanged(name) => format!("unchanged {}", name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exported_volumes_are_unchanged() {
+        let current = server_volumes();
+        let manifest = Manifest::from_volumes(current.clone()).unwrap();
+        let plan = plan(manifest.volumes, &current, true).unwrap();
+        assert_eq!(summary(&plan), ["unchanged proj", "unchanged home"]);
+    }
+
+    #[test]
+    fn only_fields_in_the_manifest_are_compared() {
+        let desired = vec![volume(json!({"vol": "proj", "ignored_dirs": [".snapshot", "tmp"]}))];
+        let plan = plan(desired, &server_volumes(), false).unwrap();
+        assert_eq!(summary(&plan), ["update proj (ignored_dirs)"]);
+        let Action::Update(_, fields) = &plan[0] else { unreachable!() };
+        assert_eq!(fields["ignored_dirs"], json!([".snapshot", "tmp"]));
+    }
+
+    #[test]
+    fn read_only_fields_are_ignored() {
+        let desired = vec![volume(json!({"vol": "proj", "id": 9, "total_size": 0}))];
+        let plan = plan(desired, &server_volumes(), false).unwrap();
+        assert_eq!(summary(&plan), ["unchanged proj"]);
+    }
+
+    #[test]
+    fn creates_and_prunes_volumes() {
+        let desired = vec![
+            volume(json!({"vol": "proj"})),
+            volume(json!({"vol": "scratch", "type": "linux"})),
+        ];
+        let current = server_volumes();
+        let plan_with = |prune| summary(&plan(desired.clone(), &current, prune).unwrap());
+        assert_eq!(plan_with(false), ["unchanged proj", "create scratch"]);
+        assert_eq!(plan_with(true), ["unchanged proj", "create scratch", "delete home"]);
+    }
+
+    #[test]
+    fn unknown_fields_are_an_error() {
+        let desired = vec![volume(json!({"vol": "proj", "ignored_dir": ["x"]}))];
+        let err = plan(desired, &server_volumes(), false).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Unknown field 'ignored_dir' for volume proj in the manifest"
+        );
+    }
+
+    #[test]
+    fn fields_the_server_reports_are_known() {
+        let desired = vec![volume(json!({"vol": "home", "total_capacity": 8192}))];
+        let plan = plan(desired, &server_volumes(), false).unwrap();
+        assert_eq!(summary(&plan), ["update home (total_capacity)"]);
+    }
+}
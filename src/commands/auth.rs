@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::io::{BufRead, IsTerminal, Write};
+
+use crate::auth;
+use rsf::{Auth, Client, Profile};
+
+#[derive(Args)]
+pub struct LoginArgs {
+    /// User to log in as [default: username from the profile]
+    #[arg(long, short)]
+    username: Option<String>,
+    /// Read the password from stdin instead of prompting
+    #[arg(long)]
+    password_stdin: bool,
+}
+
+pub async fn handle_login_command(profile: &Profile, args: LoginArgs) -> Result<()> {
+    let username = match args.username.or_else(|| profile.username.clone()) {
+        Some(username) => username,
+        None => prompt_username()?,
+    };
+
+    let password = if args.password_stdin {
+        let mut password = String::new();
+        std::io::stdin()
+            .lock()
+            .read_line(&mut password)
+            .context("Failed to read password from stdin")?;
+        password.trim_end_matches(['\r', '\n']).to_string()
+    } else {
+        auth::prompt_password(&username)?
+    };
+
+    auth::login(profile, &username, &password).await?;
+    println!(
+        "Logged in to {} as {} (profile {})",
+        profile.base_url(),
+        username,
+        profile.name
+    );
+    Ok(())
+}
+
+pub async fn handle_logout_command(profile: &Profile) -> Result<()> {
+    let Some(session) = auth::load_token(profile)? else {
+        // Drop a token cached for another server too, it is of no use for this profile
+        auth::remove_token(profile)?;
+        println!("Not logged in (profile {})", profile.name);
+        return Ok(());
+    };
+
+    if !session.is_expired() {
+        // Revoking is best effort, the local token is dropped either way
+        let client = Client::new(profile, &Auth::Bearer(session.token.clone()))?;
+        if let Err(err) = client.logout(&session).await {
+            eprintln!("Warning: failed to revoke token on the server: {}", err);
+        }
+    }
+    auth::remove_token(profile)?;
+    println!("Logged out (profile {})", profile.name);
+    Ok(())
+}
+
+fn prompt_username() -> Result<String> {
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("No username configured, pass --username");
+    }
+    print!("Username: ");
+    std::io::stdout().flush()?;
+    let mut username = String::new();
+    std::io::stdin().lock().read_line(&mut username)?;
+    Ok(username.trim().to_string())
+}
@@ -0,0 +1,133 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use futures::future::{self, try_join_all};
+use futures::TryStreamExt;
+use rsf::client::DEFAULT_PAGE_SIZE;
+use rsf::models::{Entry, EntryType, VolumePath};
+use rsf::{Client, FileQuery, GroupBy};
+use serde::Serialize;
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::humanize::Size;
+use crate::output::{ListOptions, Renderer, Tabular};
+
+#[derive(Args)]
+pub struct DuArgs {
+    /// Volume and path to summarize, as VOLUME:PATH
+    target: VolumePath,
+    /// Report every directory up to this many levels below the path, like `du -d`
+    #[arg(long, short, value_name = "N", default_value_t = 1)]
+    depth: u32,
+    /// Order of the directories; the total always comes last
+    #[arg(long, value_enum, default_value_t = DuSort::Path)]
+    sort: DuSort,
+    /// Print sizes with units such as MiB instead of bytes
+    #[arg(long, short = 'H')]
+    human: bool,
+    #[command(flatten)]
+    options: ListOptions,
+    /// Entries fetched from the server per request
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_PAGE_SIZE)]
+    page_size: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DuSort {
+    /// By path
+    Path,
+    /// Largest first
+    Size,
+}
+
+#[derive(Serialize)]
+struct DuRow {
+    path: String,
+    size: Size,
+    files: u64,
+}
+
+impl Tabular for DuRow {
+    const FIELDS: &'static [&'static str] = &["path", "size", "files"];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &["size", "files", "path"];
+}
+
+pub async fn handle_du_command(client: &Client, args: DuArgs, renderer: &Renderer) -> Result<()> {
+    let files = FileQuery {
+        entry_type: Some(EntryType::File),
+        ..Default::default()
+    };
+    // Listed separately from the sizes, which only know directories with files below them
+    let dirs: Vec<Entry> = if args.depth > 0 {
+        client
+            .list_tree(&args.target, args.depth, args.page_size)
+            .try_filter(|entry| future::ready(entry.is_dir()))
+            .try_collect()
+            .await?
+    } else {
+        Vec::new()
+    };
+    // Files directly in a directory are not grouped under it at its own depth, so every
+    // level only counts files further down
+    let target = &args.target;
+    let levels = try_join_all((1..=args.depth).map(|level| {
+        let nested = FileQuery {
+            min_depth: Some(level + 1),
+            ..files.clone()
+        };
+        async move { client.query_groups(target, &nested, GroupBy::Depth(level)).await }
+    }))
+    .await?;
+    let total = client
+        .query_groups(&args.target, &files, GroupBy::Depth(0))
+        .await?;
+
+    // Groups are keyed by the directory path relative to the target
+    let groups: HashMap<String, (u64, u64)> = levels
+        .into_iter()
+        .flatten()
+        .map(|group| {
+            let subdir = match group.group {
+                Value::String(subdir) => subdir,
+                other => other.to_string(),
+            };
+            (subdir, (group.size, group.count))
+        })
+        .collect();
+
+    let row = |subdir: &str, bytes: u64, files: u64| {
+        let path = match (args.target.path.as_str(), subdir) {
+            (base, "") => base.to_string(),
+            ("", sub) => sub.to_string(),
+            (base, sub) => format!("{}/{}", base, sub),
+        };
+        DuRow {
+            path: VolumePath { volume: args.target.volume.clone(), path }.to_string(),
+            size: Size { bytes, human: args.human },
+            files,
+        }
+    };
+    let mut rows: Vec<DuRow> = dirs
+        .iter()
+        .map(|dir| {
+            let path = dir.path();
+            let subdir = match args.target.path.as_str() {
+                "" => path.as_str(),
+                base => path.strip_prefix(base).unwrap_or(&path).trim_start_matches('/'),
+            };
+            let (bytes, files) = groups.get(subdir).copied().unwrap_or_default();
+            row(subdir, bytes, files)
+        })
+        .collect();
+    match args.sort {
+        DuSort::Path => rows.sort_by(|a, b| a.path.cmp(&b.path)),
+        DuSort::Size => rows.sort_by_key(|row| Reverse(row.size.bytes)),
+    }
+    rows.push(row(
+        "",
+        total.iter().map(|group| group.size).sum(),
+        total.iter().map(|group| group.count).sum(),
+    ));
+    renderer.list(&rows, &args.options)
+}
@@ -0,0 +1,30 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use rsf::Client;
+use std::io::Write;
+
+use crate::manifest::{Manifest, ManifestFormat};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// What to export
+    #[arg(value_enum)]
+    resource: ExportResource,
+    /// Manifest format
+    #[arg(long, value_enum, default_value_t)]
+    format: ManifestFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportResource {
+    /// All volumes, in the format read by `apply`
+    Volumes,
+}
+
+pub async fn handle_export_command(client: &Client, args: ExportArgs) -> Result<()> {
+    let manifest = match args.resource {
+        ExportResource::Volumes => Manifest::from_volumes(client.get_volumes().await?)?,
+    };
+    write!(std::io::stdout(), "{}", manifest.to_string(args.format)?)?;
+    Ok(())
+}
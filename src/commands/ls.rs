@@ -0,0 +1,104 @@
+use anyhow::Result;
+use clap::Args;
+use futures::TryStreamExt;
+use rsf::client::DEFAULT_PAGE_SIZE;
+use rsf::models::{Entry, VolumePath};
+use rsf::Client;
+use serde::Serialize;
+
+use crate::humanize::{self, Size};
+use crate::output::{ListOptions, OutputFormat, Renderer, Tabular};
+
+const LONG_COLUMNS: &[&str] = &["mode", "owner", "group", "size", "mtime", "tags", "name"];
+
+#[derive(Args)]
+pub struct LsArgs {
+    /// Directory to list, as VOLUME:PATH
+    target: VolumePath,
+    /// Show the mode, owner, size, modification time and tags of each entry
+    #[arg(short, long)]
+    long: bool,
+    /// Print sizes with units such as MiB instead of bytes
+    #[arg(long, short = 'H')]
+    human: bool,
+    #[command(flatten)]
+    options: ListOptions,
+    /// Entries fetched from the server per request
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_PAGE_SIZE)]
+    page_size: usize,
+}
+
+/// Entry as shown in table output, with times, modes and owners written for people.
+#[derive(Serialize)]
+struct LsRow {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: String,
+    mode: String,
+    owner: String,
+    group: String,
+    size: Size,
+    mtime: String,
+    atime: String,
+    ctime: String,
+    tags: String,
+}
+
+impl Tabular for LsRow {
+    const FIELDS: &'static [&'static str] = &[
+        "name", "path", "type", "mode", "owner", "group", "size", "mtime", "atime", "ctime",
+        "tags",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &["name"];
+}
+
+impl LsRow {
+    fn new(entry: &Entry, human: bool) -> Self {
+        let time = |secs: Option<i64>| {
+            secs.map(|secs| humanize::local_time(secs as f64))
+                .unwrap_or_default()
+        };
+        let id = |id: Option<u32>| id.map(|id| id.to_string()).unwrap_or_default();
+        Self {
+            name: entry.name.clone(),
+            path: entry.path(),
+            entry_type: entry
+                .entry_type
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            mode: humanize::mode(entry.entry_type.as_ref(), entry.mode),
+            owner: entry.username.clone().unwrap_or_else(|| id(entry.uid)),
+            group: entry.groupname.clone().unwrap_or_else(|| id(entry.gid)),
+            size: Size { bytes: entry.size.unwrap_or(0), human },
+            mtime: time(entry.mtime),
+            atime: time(entry.atime),
+            ctime: time(entry.ctime),
+            tags: entry.tags.join(","),
+        }
+    }
+}
+
+pub async fn handle_ls_command(client: &Client, args: LsArgs, renderer: &Renderer) -> Result<()> {
+    let mut entries: Vec<Entry> = client
+        .list_dir(&args.target, args.page_size)
+        .try_collect()
+        .await?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // Other formats are read by scripts, which get the records as the server sends them
+    if renderer.format() != OutputFormat::Table {
+        return renderer.list(&entries, &args.options);
+    }
+    let rows: Vec<LsRow> = entries
+        .iter()
+        .map(|entry| LsRow::new(entry, args.human))
+        .collect();
+    let options = if args.long {
+        args.options.or_columns(LONG_COLUMNS)
+    } else {
+        args.options
+    };
+    renderer.list(&rows, &options)
+}
@@ -1,8 +1,96 @@
+pub mod api;
+pub mod apply;
+pub mod auth;
+pub mod du;
+pub mod export;
+pub mod ls;
+pub mod query;
 pub mod scan;
+pub mod stat;
+pub mod tree;
 pub mod volume;
 pub mod ui;
 
+use anyhow::{anyhow, bail, Result};
 use clap::Subcommand;
+use serde_json::{Map, Value};
+use std::io::{BufRead, IsTerminal, Write};
+use std::str::FromStr;
+
+/// Error for command outcomes that scripts tell apart by exit code.
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct CommandFailed {
+    pub code: i32,
+    pub message: String,
+}
+
+impl CommandFailed {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+/// Asks a yes/no question on the terminal; anything but yes counts as no.
+pub fn confirm(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        bail!("Cannot ask for confirmation without a terminal, pass --yes to go ahead");
+    }
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// A `KEY=VALUE` argument setting a field of a JSON request body.
+#[derive(Clone, Debug)]
+pub struct Setting {
+    path: Vec<String>,
+    value: Value,
+}
+
+impl FromStr for Setting {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let (key, value) = expr
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid setting '{}', expected KEY=VALUE", expr))?;
+        let path: Vec<String> = key.trim().split('.').map(str::to_string).collect();
+        if path.iter().any(String::is_empty) {
+            bail!("Invalid setting '{}', missing field name", expr);
+        }
+        // Plain words such as `linux` are not JSON, so they are taken as strings
+        let value =
+            serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+        Ok(Self { path, value })
+    }
+}
+
+/// Merges settings into a JSON object, e.g. the body of a partial update.
+pub fn settings_object(settings: Vec<Setting>) -> Result<Map<String, Value>> {
+    let mut object = Map::new();
+    for setting in settings {
+        let (last, parents) = setting.path.split_last().expect("paths are never empty");
+        let mut map = &mut object;
+        for key in parents {
+            let entry = map
+                .entry(key.clone())
+                .or_insert_with(|| Value::Object(Map::new()));
+            map = match entry {
+                Value::Object(map) => map,
+                _ => bail!(
+                    "Cannot set '{}', '{}' is already set to a value",
+                    setting.path.join("."),
+                    key
+                ),
+            };
+        }
+        map.insert(last.clone(), setting.value);
+    }
+    Ok(object)
+}
 
 #[derive(Subcommand)]
 pub enum Commands {
@@ -18,4 +106,24 @@ pub enum Commands {
     },
     /// Interactive UI mode
     Ui,
+    /// Create, update and optionally delete volumes to match a manifest file
+    Apply(apply::ApplyArgs),
+    /// Print server configuration as a manifest for `apply`
+    Export(export::ExportArgs),
+    /// Send a request to any API endpoint and print the response
+    Api(api::ApiArgs),
+    /// Search the metadata index for files and directories
+    Query(Box<query::QueryArgs>),
+    /// Show the size and file count of directories from the metadata index
+    Du(du::DuArgs),
+    /// List the entries of a directory from the metadata index
+    Ls(ls::LsArgs),
+    /// Show the indexed metadata of a file or directory
+    Stat(stat::StatArgs),
+    /// Draw a directory from the metadata index as a tree with sizes
+    Tree(tree::TreeArgs),
+    /// Log in and cache an API token for the profile
+    Login(auth::LoginArgs),
+    /// Revoke and forget the cached API token for the profile
+    Logout,
 } 
\ No newline at end of file
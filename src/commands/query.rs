@@ -0,0 +1,395 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use clap::{Args, ValueEnum};
+use futures::TryStreamExt;
+use regex::Regex;
+use rsf::client::DEFAULT_PAGE_SIZE;
+use rsf::models::{EntryType, VolumePath};
+use rsf::{Client, FileQuery, GroupBy};
+use std::cmp::Reverse;
+use std::str::FromStr;
+
+use crate::filter::compare;
+use crate::humanize;
+use crate::output::{ListOptions, Renderer};
+
+#[derive(Args)]
+pub struct QueryArgs {
+    /// Volume and path to search under, as VOLUME:PATH
+    target: VolumePath,
+    #[command(flatten)]
+    filters: QueryFilters,
+    #[command(flatten)]
+    options: ListOptions,
+    /// Entries fetched from the server per request
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_PAGE_SIZE)]
+    page_size: usize,
+    /// Stop after fetching this many entries from the server
+    #[arg(long, value_name = "N", conflicts_with = "group_by")]
+    max_items: Option<usize>,
+    /// Show the entry count and total size per uid, gid, ext, depth=N or mtime-bucket
+    /// instead of listing entries
+    #[arg(long, value_name = "GROUP")]
+    group_by: Option<GroupBy>,
+    /// Order of the groups
+    #[arg(long, value_enum, default_value_t = GroupSort::Size, requires = "group_by")]
+    sort: GroupSort,
+    /// Show at most this many groups
+    #[arg(long, value_name = "N", requires = "group_by")]
+    limit: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupSort {
+    /// Largest total size first
+    Size,
+    /// Most entries first
+    Count,
+    /// By group value
+    Group,
+}
+
+/// Filters on indexed metadata, shared by commands that search the index.
+#[derive(Args)]
+pub struct QueryFilters {
+    /// Only entries whose name matches this glob, e.g. '*.log'
+    #[arg(long, value_name = "GLOB")]
+    name: Option<String>,
+    /// Only entries whose name matches this regular expression
+    #[arg(long, value_name = "REGEX", value_parser = parse_regex)]
+    regex: Option<String>,
+    /// Only entries of this type
+    #[arg(long = "type", value_enum)]
+    entry_type: Option<EntryTypeArg>,
+    /// Size range MIN..MAX with optional K, M, G, T or P suffixes, e.g. 1G.. or ..10M;
+    /// a single size matches exactly
+    #[arg(long, value_name = "RANGE")]
+    size: Option<SizeRange>,
+    /// Modification time range FROM..TO, each a date such as 2024-01-31, a date and time
+    /// such as 2024-01-31T12:00, or an age such as 30d; e.g. ..90d for older than 90 days.
+    /// A single date matches that whole day, and a single age means older than it
+    #[arg(long, value_name = "RANGE")]
+    mtime: Option<TimeRange>,
+    /// Access time range, written like --mtime
+    #[arg(long, value_name = "RANGE")]
+    atime: Option<TimeRange>,
+    /// Status change time range, written like --mtime
+    #[arg(long, value_name = "RANGE")]
+    ctime: Option<TimeRange>,
+    /// Only entries owned by this user id
+    #[arg(long)]
+    uid: Option<u32>,
+    /// Only entries owned by this group id
+    #[arg(long)]
+    gid: Option<u32>,
+    /// Only entries at least this many levels below the path; its children are at depth 1
+    #[arg(long, value_name = "N")]
+    min_depth: Option<u32>,
+    /// Only entries at most this many levels below the path
+    #[arg(long, value_name = "N")]
+    max_depth: Option<u32>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum EntryTypeArg {
+    #[value(alias = "f")]
+    File,
+    #[value(alias = "d")]
+    Dir,
+    #[value(alias = "l")]
+    Link,
+}
+
+impl From<EntryTypeArg> for EntryType {
+    fn from(arg: EntryTypeArg) -> Self {
+        match arg {
+            EntryTypeArg::File => EntryType::File,
+            EntryTypeArg::Dir => EntryType::Directory,
+            EntryTypeArg::Link => EntryType::Link,
+        }
+    }
+}
+
+/// Inclusive range written `MIN..MAX`, where either end may be left out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Range<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T: Copy> Range<T> {
+    fn parse(s: &str, bound: impl Fn(&str) -> Result<T>) -> Result<Self> {
+        let Some((min, max)) = s.split_once("..") else {
+            let value = bound(s.trim())?;
+            return Ok(Self { min: Some(value), max: Some(value) });
+        };
+        let parse_end = |end: &str| match end.trim() {
+            "" => Ok(None),
+            end => bound(end).map(Some),
+        };
+        Ok(Self { min: parse_end(min)?, max: parse_end(max)? })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SizeRange(pub Range<u64>);
+
+impl FromStr for SizeRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Range::parse(s, parse_size).map(Self)
+    }
+}
+
+/// Time range in seconds since the Unix epoch.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeRange(pub Range<i64>);
+
+impl FromStr for TimeRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if !s.contains("..") {
+            return parse_time_single(s.trim()).map(Self);
+        }
+        let range = Range::parse(s, parse_time)?;
+        // Ages count backwards, so `30d..7d` and `7d..30d` mean the same
+        let (min, max) = match (range.min, range.max) {
+            (Some(min), Some(max)) if min.0 > max.0 => (Some(max), Some(min)),
+            bounds => bounds,
+        };
+        // A date or time as the upper bound includes the whole day, minute or second
+        Ok(Self(Range {
+            min: min.map(|(first, _)| first),
+            max: max.map(|(_, last)| last),
+        }))
+    }
+}
+
+/// Checks a regular expression before it is sent to the server.
+fn parse_regex(s: &str) -> Result<String> {
+    Regex::new(s)?;
+    Ok(s.to_string())
+}
+
+/// Parses a size such as `512`, `10K`, `1.5G` or `2TiB`, with binary units.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid size '{}', expected e.g. 10M or 1.5G", s))?;
+    let unit = unit.trim().to_uppercase();
+    let exponent = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        _ => bail!("Invalid size unit in '{}', expected one of K, M, G, T, P", s),
+    };
+    Ok((number * 1024f64.powi(exponent)) as u64)
+}
+
+/// Parses a point in time: a local date, a local date and time, or an age such as `30d`
+/// counted back from now. Returns the first and last second the value covers, which are
+/// the same for an age.
+pub fn parse_time(s: &str) -> Result<(i64, i64)> {
+    if let Ok(age) = humantime::parse_duration(s) {
+        let time = (humanize::now() - age.as_secs_f64()) as i64;
+        return Ok((time, time));
+    }
+    parse_local_time(s)
+        .map(|(start, end)| (start, end - 1))
+        .ok_or_else(|| invalid_time(s))
+}
+
+/// Time range for a single value: an age means older than it, and a date or date and
+/// time covers the whole day, minute or second it names.
+fn parse_time_single(s: &str) -> Result<Range<i64>> {
+    let (first, last) = parse_time(s)?;
+    if humantime::parse_duration(s).is_ok() {
+        return Ok(Range { min: None, max: Some(first) });
+    }
+    Ok(Range { min: Some(first), max: Some(last) })
+}
+
+/// Start and exclusive end of a local date or date and time, in seconds since the epoch.
+fn parse_local_time(s: &str) -> Option<(i64, i64)> {
+    let (start, length) = if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        (date.and_hms_opt(0, 0, 0)?, TimeDelta::days(1))
+    } else {
+        [
+            ("%Y-%m-%dT%H:%M:%S", TimeDelta::seconds(1)),
+            ("%Y-%m-%dT%H:%M", TimeDelta::minutes(1)),
+            ("%Y-%m-%d %H:%M:%S", TimeDelta::seconds(1)),
+            ("%Y-%m-%d %H:%M", TimeDelta::minutes(1)),
+        ]
+        .into_iter()
+        .find_map(|(format, length)| {
+            NaiveDateTime::parse_from_str(s, format).ok().map(|time| (time, length))
+        })?
+    };
+    let timestamp = |time| Local.from_local_datetime(&time).earliest().map(|t| t.timestamp());
+    Some((timestamp(start)?, timestamp(start + length)?))
+}
+
+fn invalid_time(s: &str) -> anyhow::Error {
+    anyhow!("Invalid time '{}', expected e.g. 2024-01-31, 2024-01-31T12:00 or 30d", s)
+}
+
+impl QueryFilters {
+    /// Server-side query for these filters.
+    pub fn to_query(&self) -> FileQuery {
+        let size = self.size.map(|range| range.0);
+        let mtime = self.mtime.map(|range| range.0);
+        let atime = self.atime.map(|range| range.0);
+        let ctime = self.ctime.map(|range| range.0);
+        FileQuery {
+            name: self.name.clone(),
+            name_regex: self.regex.clone(),
+            entry_type: self.entry_type.map(Into::into),
+            size_min: size.and_then(|range| range.min),
+            size_max: size.and_then(|range| range.max),
+            mtime_min: mtime.and_then(|range| range.min),
+            mtime_max: mtime.and_then(|range| range.max),
+            atime_min: atime.and_then(|range| range.min),
+            atime_max: atime.and_then(|range| range.max),
+            ctime_min: ctime.and_then(|range| range.min),
+            ctime_max: ctime.and_then(|range| range.max),
+            uid: self.uid,
+            gid: self.gid,
+            min_depth: self.min_depth,
+            max_depth: self.max_depth,
+            limit: None,
+        }
+    }
+}
+
+pub async fn handle_query_command(
+    client: &Client,
+    args: QueryArgs,
+    renderer: &Renderer,
+) -> Result<()> {
+    if let Some(group_by) = args.group_by {
+        let query = args.filters.to_query();
+        let mut groups = client.query_groups(&args.target, &query, group_by).await?;
+        match args.sort {
+            GroupSort::Size => groups.sort_by_key(|group| Reverse(group.size)),
+            GroupSort::Count => groups.sort_by_key(|group| Reverse(group.count)),
+            GroupSort::Group => groups.sort_by(|a, b| compare(Some(&a.group), Some(&b.group))),
+        }
+        if let Some(limit) = args.limit {
+            groups.truncate(limit);
+        }
+        return renderer.list(&groups, &args.options);
+    }
+
+    let query = FileQuery {
+        limit: args.max_items,
+        ..args.filters.to_query()
+    };
+    let entries = client
+        .query(&args.target, &query, args.page_size)
+        .map_err(anyhow::Error::from);
+    renderer.list_stream(entries, &args.options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Local midnight starting `date`, in seconds since the epoch.
+    fn midnight(date: &str) -> i64 {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        let time = date.and_hms_opt(0, 0, 0).unwrap();
+        Local.from_local_datetime(&time).earliest().unwrap().timestamp()
+    }
+
+    fn time_range(s: &str) -> Range<i64> {
+        s.parse::<TimeRange>().unwrap().0
+    }
+
+    #[test]
+    fn parses_sizes_with_binary_units() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10K").unwrap(), 10 * 1024);
+        assert_eq!(parse_size("1.5G").unwrap(), 3 << 29);
+        assert_eq!(parse_size("2TiB").unwrap(), 2 << 40);
+        assert_eq!(parse_size("3 mb").unwrap(), 3 << 20);
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn parses_open_and_closed_ranges() {
+        let range = |s| Range::parse(s, parse_size).unwrap();
+        assert_eq!(range("1K..2K"), Range { min: Some(1024), max: Some(2048) });
+        assert_eq!(range("1K.."), Range { min: Some(1024), max: None });
+        assert_eq!(range("..2K"), Range { min: None, max: Some(2048) });
+        assert_eq!(range(" 1K .. 2K "), Range { min: Some(1024), max: Some(2048) });
+        assert_eq!(range("1K"), Range { min: Some(1024), max: Some(1024) });
+        assert!(Range::parse("1K..2X", parse_size).is_err());
+    }
+
+    #[test]
+    fn date_ranges_include_the_whole_last_day() {
+        let range = time_range("2024-01-01..2024-01-31");
+        assert_eq!(range.min, Some(midnight("2024-01-01")));
+        assert_eq!(range.max, Some(midnight("2024-02-01") - 1));
+
+        // Reversed ends are swapped, still covering both days whole
+        assert_eq!(time_range("2024-01-31..2024-01-01"), range);
+    }
+
+    #[test]
+    fn open_date_ranges() {
+        let since = time_range("2024-01-31..");
+        assert_eq!(since, Range { min: Some(midnight("2024-01-31")), max: None });
+        let until = time_range("..2024-01-31");
+        assert_eq!(until, Range { min: None, max: Some(midnight("2024-02-01") - 1) });
+    }
+
+    #[test]
+    fn single_values_cover_the_time_they_name() {
+        let day = time_range("2024-01-31");
+        assert_eq!(day.min, Some(midnight("2024-01-31")));
+        assert_eq!(day.max, Some(midnight("2024-02-01") - 1));
+
+        let minute = time_range("2024-01-31T12:30");
+        let start = midnight("2024-01-31") + 12 * 3600 + 30 * 60;
+        assert_eq!(minute, Range { min: Some(start), max: Some(start + 59) });
+
+        let second = time_range("2024-01-31 12:30:15");
+        assert_eq!(second, Range { min: Some(start + 15), max: Some(start + 15) });
+    }
+
+    #[test]
+    fn ages_count_back_from_now() {
+        let now = humanize::now() as i64;
+        let day = 24 * 3600;
+
+        // A single age means older than it
+        let older = time_range("7d");
+        assert_eq!(older.min, None);
+        assert!((older.max.unwrap() - (now - 7 * day)).abs() <= 1);
+
+        // Either order gives the window between the two ages
+        for s in ["30d..7d", "7d..30d"] {
+            let range = time_range(s);
+            assert!((range.min.unwrap() - (now - 30 * day)).abs() <= 1);
+            assert!((range.max.unwrap() - (now - 7 * day)).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        assert!("yesterday".parse::<TimeRange>().is_err());
+        assert!("2024-13-01".parse::<TimeRange>().is_err());
+        assert!("2024-01-01..soon".parse::<TimeRange>().is_err());
+    }
+}
@@ -1,54 +1,494 @@
-use anyhow::{Context, Result};
-use clap::Subcommand;
-use crate::client::Client;
+use anyhow::Result;
+use clap::{Args, Subcommand, ValueEnum};
+use crossterm::cursor::MoveToColumn;
+use crossterm::queue;
+use crossterm::style::Print;
+use crossterm::terminal::{Clear, ClearType};
+use rsf::models::{CrawlerOptions, Scan, ScanState, ScanType, StartScanRequest, VolumePath};
+use futures::{future, Stream, TryStreamExt};
+use rsf::client::DEFAULT_PAGE_SIZE;
+use rsf::{Client, RsfError, ScanQuery};
+use std::io::{IsTerminal, Write};
+use std::pin::pin;
+use std::time::{Duration, Instant};
+
+use super::CommandFailed;
+use crate::filter::FilterOptions;
+use crate::humanize;
+use crate::output::{terminal_width, truncate, ListOptions, OutputFormat, Renderer, Report};
+
+/// The scan finished with an error
+const EXIT_SCAN_FAILED: i32 = 10;
+/// The scan was stopped or cancelled before finishing
+const EXIT_SCAN_CANCELLED: i32 = 11;
+/// The scan did not reach the expected state in time
+const EXIT_TIMEOUT: i32 = 12;
+/// The scan ended up in a state other than the one asked for
+const EXIT_UNEXPECTED_STATE: i32 = 13;
 
 #[derive(Subcommand)]
 pub enum ScanCommands {
     /// List all scans
     List {
-        /// Output full JSON response
-        #[arg(long)]
-        json: bool,
+        #[command(flatten)]
+        filter: FilterOptions,
+        #[command(flatten)]
+        options: ListOptions,
+        /// Scans fetched from the server per request
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_PAGE_SIZE)]
+        page_size: usize,
+        /// Stop after fetching this many scans from the server
+        #[arg(long, value_name = "N")]
+        max_items: Option<usize>,
     },
     /// Show details of a specific scan
     Show {
         /// ID of the scan
         id: String,
-        /// Output full JSON response
+    },
+    /// Start a new scan and print its id
+    Start {
+        /// What to scan, as VOLUME[:PATH]
+        target: VolumePath,
+        /// Kind of scan
+        #[arg(long = "type", value_enum, default_value_t = ScanTypeArg::Diff)]
+        scan_type: ScanTypeArg,
+        /// Number of crawler threads
+        #[arg(long)]
+        threads: Option<u32>,
+        /// How many directory levels below PATH to descend
+        #[arg(long)]
+        depth: Option<u32>,
+        /// Agent to run the crawler on instead of the volume default
         #[arg(long)]
-        json: bool,
+        agent: Option<String>,
+    },
+    /// Stop a running scan
+    Stop(TransitionArgs),
+    /// Pause a running scan
+    Pause(TransitionArgs),
+    /// Resume a paused scan
+    Resume(TransitionArgs),
+    /// Wait for a scan to finish
+    ///
+    /// Exits with 0 when the scan is done, 10 when it failed, 11 when it was stopped or
+    /// cancelled and 12 when the timeout passed first.
+    Wait {
+        /// ID of the scan
+        id: String,
+        /// Give up after this long, e.g. 2h or 30m [default: wait forever]
+        #[arg(long, value_parser = humantime::parse_duration)]
+        timeout: Option<Duration>,
+        /// How often to check the scan state
+        #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
+        interval: Duration,
+        /// Print the final scan in the --output format
+        #[arg(long)]
+        print: bool,
+    },
+    /// Follow the progress of a scan until it finishes
+    ///
+    /// Redraws a single status line on a terminal and prints one line per update
+    /// otherwise. Exits with the same codes as `scan wait`.
+    Watch {
+        /// ID of the scan
+        id: String,
+        /// How often to refresh the progress
+        #[arg(long, value_parser = humantime::parse_duration, default_value = "2s")]
+        interval: Duration,
     },
 }
 
-pub async fn handle_scan_command(client: &Client, command: ScanCommands) -> Result<()> {
+#[derive(Args)]
+pub struct TransitionArgs {
+    /// ID of the scan
+    id: String,
+    /// How long to wait for the scan to reach the new state
+    #[arg(long, value_parser = humantime::parse_duration, default_value = "60s")]
+    timeout: Duration,
+    /// How often to check the scan state
+    #[arg(long, value_parser = humantime::parse_duration, default_value = "2s")]
+    interval: Duration,
+    /// Return right after sending the request instead of waiting for the new state
+    #[arg(long)]
+    no_wait: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ScanTypeArg {
+    /// Find changes since the previous scan
+    Diff,
+    /// Only look at entries with a changed modification time
+    Mtime,
+    /// Full crawl that resynchronizes the index
+    Sync,
+}
+
+impl From<ScanTypeArg> for ScanType {
+    fn from(arg: ScanTypeArg) -> Self {
+        match arg {
+            ScanTypeArg::Diff => ScanType::Diff,
+            ScanTypeArg::Mtime => ScanType::Mtime,
+            ScanTypeArg::Sync => ScanType::Sync,
+        }
+    }
+}
+
+pub async fn handle_scan_command(
+    client: &Client,
+    command: ScanCommands,
+    renderer: &Renderer,
+) -> Result<()> {
     match command {
-        ScanCommands::List { json } => {
-            let response = client.get_scans().await?;
-            if json {
-                println!("{}", serde_json::to_string_pretty(&response)?);
+        ScanCommands::List { filter, options, page_size, max_items } => {
+            let mut query = server_query(&filter);
+            query.limit = match (query.limit, max_items) {
+                (Some(limit), Some(max)) => Some(limit.min(max)),
+                (limit, max) => limit.or(max),
+            };
+            let scans = client.scans(&query, page_size).map_err(anyhow::Error::from);
+            if filter.needs_all_items() {
+                let scans = filter.apply(scans.try_collect().await?)?;
+                renderer.list(&scans, &options)?;
             } else {
-                let scans = response["scans"].as_array().context("Expected scans array")?;
-                for scan in scans {
-                    if let Some(id) = scan["id"].as_str() {
-                        println!("{}", id);
-                    }
-                }
+                renderer.list_stream(filter.apply_stream(scans), &options).await?;
             }
         }
-        ScanCommands::Show { id, json } => {
-            match client.get_scan(&id).await? {
-                Some(scan) => {
-                    if json {
-                        println!("{}", serde_json::to_string_pretty(&scan)?);
-                    } else {
-                        let scan_id = scan["id"].as_str().unwrap_or("");
-                        let volume = scan["volume"].as_str().unwrap_or("");
-                        println!("{} {}", scan_id, volume);
-                    }
+        ScanCommands::Show { id } => {
+            let scan = fetch_scan(client, &id).await?;
+            if renderer.format() == OutputFormat::Table {
+                scan_report(&scan).print()?;
+            } else {
+                renderer.item(&scan)?;
+            }
+        }
+        ScanCommands::Start { target, scan_type, threads, depth, agent } => {
+            let request = StartScanRequest {
+                volume: target,
+                scan_type: scan_type.into(),
+                crawler_options: CrawlerOptions {
+                    threads,
+                    max_depth: depth,
+                    agent_address: agent,
+                },
+            };
+            let scan = client.start_scan(&request).await?;
+            if renderer.format() == OutputFormat::Table {
+                println!("{}", scan.id);
+            } else {
+                renderer.item(&scan)?;
+            }
+        }
+        ScanCommands::Stop(args) => {
+            client.stop_scan(&args.id).await?;
+            // Servers report a stop requested by the user as either state
+            confirm_transition(client, &args, &[ScanState::Stopped, ScanState::Cancelled]).await?;
+        }
+        ScanCommands::Pause(args) => {
+            client.pause_scan(&args.id).await?;
+            confirm_transition(client, &args, &[ScanState::Paused]).await?;
+        }
+        ScanCommands::Resume(args) => {
+            client.resume_scan(&args.id).await?;
+            // A short scan may already be done by the time it is checked
+            confirm_transition(client, &args, &[ScanState::Running, ScanState::Done]).await?;
+        }
+        ScanCommands::Wait { id, timeout, interval, print } => {
+            let (scan, finished) = poll_scan(client, &id, interval, timeout, |scan| {
+                scan.state.as_ref().is_some_and(ScanState::is_final)
+            })
+            .await?;
+            if print {
+                renderer.item(&scan)?;
+            }
+
+            if !finished {
+                let state = scan.state.as_ref().map_or("unknown", ScanState::as_str);
+                let waited = humantime::format_duration(timeout.unwrap_or_default());
+                let message = format!("Scan {} is still {} after {}", scan.id, state, waited);
+                return Err(CommandFailed::new(EXIT_TIMEOUT, message).into());
+            }
+            check_outcome(&scan)?;
+            if !print {
+                println!("Scan {} is done", scan.id);
+            }
+        }
+        ScanCommands::Watch { id, interval } => {
+            let mut scan = fetch_scan(client, &id).await?;
+            let expected = if scan.state.as_ref().is_some_and(ScanState::is_final) {
+                None
+            } else {
+                // Only feeds the ETA, so a failed lookup just leaves it out
+                expected_entries(client, &scan).await.ok().flatten()
+            };
+            let live = std::io::stdout().is_terminal();
+            loop {
+                let line = progress_line(&scan, expected);
+                if live {
+                    let width = terminal_width().unwrap_or(usize::MAX);
+                    let mut out = std::io::stdout().lock();
+                    queue!(
+                        out,
+                        MoveToColumn(0),
+                        Clear(ClearType::CurrentLine),
+                        Print(truncate(&line, width.saturating_sub(1)))
+                    )?;
+                    out.flush()?;
+                } else {
+                    writeln!(std::io::stdout(), "{}", line)?;
+                }
+                if scan.state.as_ref().is_some_and(ScanState::is_final) {
+                    break;
                 }
-                None => eprintln!("There is no scan {}", id),
+                tokio::time::sleep(interval).await;
+                scan = fetch_scan(client, &id).await?;
             }
+            if live {
+                println!();
+            }
+            check_outcome(&scan)?;
         }
     }
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// Waits until the scan reaches one of `expected` states, failing if it settles in another
+/// final state or the timeout passes.
+async fn confirm_transition(
+    client: &Client,
+    args: &TransitionArgs,
+    expected: &[ScanState],
+) -> Result<()> {
+    if args.no_wait {
+        return Ok(());
+    }
+
+    let (scan, reached) = poll_scan(client, &args.id, args.interval, Some(args.timeout), |scan| {
+        scan.state
+            .as_ref()
+            .is_some_and(|state| expected.contains(state) || state.is_final())
+    })
+    .await?;
+    let state = scan.state.as_ref().map_or("unknown", ScanState::as_str);
+
+    if !reached {
+        return Err(CommandFailed::new(
+            EXIT_TIMEOUT,
+            format!(
+                "Scan {} is still {} after {}",
+                scan.id,
+                state,
+                humantime::format_duration(args.timeout)
+            ),
+        )
+        .into());
+    }
+    if !scan.state.as_ref().is_some_and(|state| expected.contains(state)) {
+        return Err(CommandFailed::new(
+            EXIT_UNEXPECTED_STATE,
+            format!("Scan {} ended up {}", scan.id, state),
+        )
+        .into());
+    }
+    println!("Scan {} is {}", scan.id, state);
+    Ok(())
+}
+
+/// Fetches the scan every `interval` until `done` holds for it or `timeout`, if any, passes.
+///
+/// Returns the last version of the scan and whether `done` was reached. Network errors and
+/// server errors do not end the wait, polling goes on until the timeout.
+async fn poll_scan(
+    client: &Client,
+    id: &str,
+    interval: Duration,
+    timeout: Option<Duration>,
+    done: impl Fn(&Scan) -> bool,
+) -> Result<(Scan, bool)> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut last = None;
+    loop {
+        let error = match fetch_scan(client, id).await {
+            Ok(scan) if done(&scan) => return Ok((scan, true)),
+            Ok(scan) => {
+                last = Some(scan);
+                None
+            }
+            Err(err) if is_transient(&err) => {
+                log::info!("Failed to fetch scan {}, trying again: {:#}", id, err);
+                Some(err)
+            }
+            Err(err) => return Err(err),
+        };
+        let delay = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return match last {
+                        Some(scan) => Ok((scan, false)),
+                        // Every attempt failed, so there is an error to report
+                        None => Err(error.expect("failed fetch without a scan")),
+                    };
+                }
+                interval.min(deadline - now)
+            }
+            None => interval,
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Whether a failed request may succeed when made again later.
+fn is_transient(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<RsfError>() {
+        Some(RsfError::Transport(_)) => true,
+        Some(RsfError::ServerError(status, _)) => status.is_server_error(),
+        _ => false,
+    }
+}
+
+async fn fetch_scan(client: &Client, id: &str) -> Result<Scan> {
+    Ok(client
+        .get_scan(id)
+        .await?
+        .ok_or_else(|| RsfError::NotFound(format!("scan {}", id)))?)
+}
+
+/// Maps a finished scan to the exit code scripts check: success only when it is done.
+fn check_outcome(scan: &Scan) -> Result<()> {
+    let code = match &scan.state {
+        Some(ScanState::Done) => return Ok(()),
+        Some(ScanState::Failed) => EXIT_SCAN_FAILED,
+        _ => EXIT_SCAN_CANCELLED,
+    };
+    let state = scan.state.as_ref().map_or("unknown", ScanState::as_str);
+    Err(CommandFailed::new(code, format!("Scan {} is {}", scan.id, state)).into())
+}
+
+/// Successful scans of `volume`, most recent first.
+fn done_scans<'a>(
+    client: &'a Client,
+    volume: &'a str,
+    page_size: usize,
+) -> impl Stream<Item = rsf::Result<Scan>> + 'a {
+    let query = ScanQuery {
+        volume: Some(volume.to_string()),
+        state: Some(ScanState::Done.to_string()),
+        ..Default::default()
+    };
+    // Checked here as well, as `scan list` does, in case the server ignores the filters
+    client.scans(&query, page_size).try_filter(move |scan| {
+        future::ready(scan.volume == volume && scan.state == Some(ScanState::Done))
+    })
+}
+
+/// The most recently finished scan of `volume` that completed successfully.
+pub async fn last_successful_scan(client: &Client, volume: &str) -> Result<Option<Scan>> {
+    // A server that filters, as current ones do, sends the wanted scan first; small pages
+    // keep the cost down with one that does not
+    let mut scans = pin!(done_scans(client, volume, 10));
+    Ok(scans.try_next().await?)
+}
+
+/// Entries found by the last successful scan of the same volume, as an estimate of how
+/// many the given scan will process.
+async fn expected_entries(client: &Client, scan: &Scan) -> Result<Option<u64>> {
+    // Scans come newest first, so this stops paging at the first one that counted entries
+    let mut previous = pin!(done_scans(client, &scan.volume, DEFAULT_PAGE_SIZE)
+        .try_filter(|previous| future::ready(previous.id != scan.id && previous.entries() > 0)));
+    Ok(previous.try_next().await?.map(|previous| previous.entries()))
+}
+
+/// One-line summary of a scan in progress, e.g.
+/// `running  1,200 files  30 dirs  5m 3s  410 entries/s  45% ETA 6m 10s`.
+fn progress_line(scan: &Scan, expected: Option<u64>) -> String {
+    let now = humanize::now();
+    let mut parts = vec![
+        scan.state.as_ref().map_or("unknown", ScanState::as_str).to_string(),
+        format!("{} files", humanize::count(scan.num_files.unwrap_or(0))),
+        format!("{} dirs", humanize::count(scan.num_dirs.unwrap_or(0))),
+    ];
+    if let Some(elapsed) = scan.elapsed(now) {
+        parts.push(humanize::duration(elapsed));
+    }
+    let throughput = scan.throughput(now);
+    if let Some(throughput) = throughput {
+        parts.push(humanize::rate(throughput, "entries"));
+    }
+
+    let running = !scan.state.as_ref().is_some_and(ScanState::is_final);
+    if let (true, Some(expected), Some(throughput)) = (running, expected, throughput) {
+        let done = scan.entries();
+        if done < expected && throughput > 0.0 {
+            let eta = Duration::from_secs_f64((expected - done) as f64 / throughput);
+            let percent = done * 100 / expected;
+            parts.push(format!("{}% ETA {}", percent, humanize::duration(eta)));
+        }
+    }
+    parts.join("  ")
+}
+
+fn scan_report(scan: &Scan) -> Report {
+    let now = humanize::now();
+    let running = !scan.state.as_ref().is_some_and(ScanState::is_final);
+    let duration = scan.elapsed(now).map(|elapsed| {
+        let elapsed = humanize::duration(elapsed);
+        if running && scan.end_time.is_none() {
+            format!("{} so far", elapsed)
+        } else {
+            elapsed
+        }
+    });
+
+    let path = scan
+        .crawler_path
+        .as_ref()
+        .map(|path| format!("/{}", path.trim_start_matches('/')));
+
+    Report::default()
+        .section(format!("Scan {}", scan.id))
+        .field("Volume", &scan.volume)
+        .optional("Path", path)
+        .optional("Type", scan.scan_type.as_ref())
+        .optional("State", scan.state.as_ref())
+        .optional("Requested by", scan.requested_by.as_ref())
+        .section("Timing")
+        .optional("Started", scan.start_time.map(humanize::local_time))
+        .optional("Ended", scan.end_time.map(humanize::local_time))
+        .optional("Duration", duration)
+        .section("Progress")
+        .optional("Files", scan.num_files.map(humanize::count))
+        .optional("Directories", scan.num_dirs.map(humanize::count))
+        .optional("Errors", scan.num_errors.map(humanize::count))
+        .optional("Throughput", scan.throughput(now).map(|rate| humanize::rate(rate, "entries")))
+}
+
+/// Moves the filters the scan endpoint understands to the server.
+///
+/// The client still applies every filter afterwards, which is a no-op for these. The limit
+/// is only passed on when the server sees all filters and no different ordering is asked
+/// for, otherwise it would cut the list before the client-side work.
+fn server_query(filter: &FilterOptions) -> ScanQuery {
+    let mut query = ScanQuery::default();
+    let mut all_on_server = true;
+    for f in &filter.filters {
+        let slot = match f.field.as_str() {
+            "volume" => &mut query.volume,
+            "state" => &mut query.state,
+            "type" => &mut query.scan_type,
+            "requested_by" => &mut query.requested_by,
+            _ => {
+                all_on_server = false;
+                continue;
+            }
+        };
+        match (f.equals(), &slot) {
+            (Some(value), None) => *slot = Some(value.to_string()),
+            _ => all_on_server = false,
+        }
+    }
+    if all_on_server && filter.sort_by.is_none() && !filter.reverse {
+        query.limit = filter.limit;
+    }
+    query
+}
@@ -0,0 +1,76 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use rsf::models::{Entry, VolumePath};
+use rsf::{Client, RsfError};
+
+use crate::humanize;
+use crate::output::{cell, OutputFormat, Renderer, Report};
+
+#[derive(Args)]
+pub struct StatArgs {
+    /// File or directory to show, as VOLUME:PATH
+    target: VolumePath,
+}
+
+pub async fn handle_stat_command(
+    client: &Client,
+    args: StatArgs,
+    renderer: &Renderer,
+) -> Result<()> {
+    if args.target.path.is_empty() {
+        bail!(
+            "The volume root has no index entry, see `rsf volume show {}` instead",
+            args.target.volume
+        );
+    }
+    let entry = client
+        .get_entry(&args.target)
+        .await?
+        .ok_or_else(|| RsfError::NotFound(format!("entry {}", args.target)))?;
+    if renderer.format() == OutputFormat::Table {
+        entry_report(&entry).print()
+    } else {
+        renderer.item(&entry)
+    }
+}
+
+fn entry_report(entry: &Entry) -> Report {
+    let path = VolumePath {
+        volume: entry.volume.clone(),
+        path: entry.path(),
+    };
+    let time = |secs: Option<i64>| secs.map(|secs| humanize::local_time(secs as f64));
+    let id = |name: Option<&String>, id: Option<u32>| match (name, id) {
+        (Some(name), Some(id)) => Some(format!("{} ({})", name, id)),
+        (Some(name), None) => Some(name.clone()),
+        (None, id) => id.map(|id| id.to_string()),
+    };
+
+    let report = Report::default()
+        .section(path.to_string())
+        .optional("Type", entry.entry_type.as_ref())
+        .optional(
+            "Size",
+            entry
+                .size
+                .map(|size| format!("{} ({} bytes)", humanize::size(size), humanize::count(size))),
+        )
+        .optional(
+            "Mode",
+            entry.mode.map(|mode| {
+                format!("{} ({:04o})", humanize::mode(entry.entry_type.as_ref(), Some(mode)), mode)
+            }),
+        )
+        .optional("Owner", id(entry.username.as_ref(), entry.uid))
+        .optional("Group", id(entry.groupname.as_ref(), entry.gid))
+        .optional("Tags", (!entry.tags.is_empty()).then(|| entry.tags.join(", ")))
+        .section("Times")
+        .optional("Modified", time(entry.mtime))
+        .optional("Accessed", time(entry.atime))
+        .optional("Changed", time(entry.ctime))
+        .section("Other fields");
+    entry
+        .extra
+        .iter()
+        .fold(report, |report, (key, value)| report.field(key, cell(value)))
+}
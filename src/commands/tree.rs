@@ -0,0 +1,150 @@
+use anyhow::Result;
+use clap::Args;
+use futures::future::try_join_all;
+use futures::TryStreamExt;
+use rsf::client::DEFAULT_PAGE_SIZE;
+use rsf::models::{Entry, EntryType, VolumePath};
+use rsf::{Client, FileQuery, GroupBy};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+
+use crate::humanize;
+use crate::output::{ListOptions, OutputFormat, Renderer};
+
+#[derive(Args)]
+pub struct TreeArgs {
+    /// Directory to draw, as VOLUME:PATH
+    target: VolumePath,
+    /// Descend at most this many levels below the path
+    #[arg(long, short, value_name = "N", default_value_t = 3)]
+    depth: u32,
+    #[command(flatten)]
+    options: ListOptions,
+    /// Entries fetched from the server per request
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_PAGE_SIZE)]
+    page_size: usize,
+}
+
+/// Entries below the target, keyed by their path relative to it.
+struct Tree<'a> {
+    children: HashMap<&'a str, Vec<(&'a str, &'a Entry)>>,
+    /// Total size of the files below each directory
+    sizes: HashMap<String, u64>,
+    width: usize,
+}
+
+impl Tree<'_> {
+    fn size(&self, path: &str, entry: &Entry) -> u64 {
+        if entry.is_dir() {
+            self.sizes.get(path).copied().unwrap_or(0)
+        } else {
+            entry.size.unwrap_or(0)
+        }
+    }
+
+    fn draw(&self, out: &mut impl Write, path: &str, prefix: &str) -> Result<()> {
+        let Some(children) = self.children.get(path) else {
+            return Ok(());
+        };
+        for (i, (child, entry)) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let size = humanize::size(self.size(child, entry));
+            let branch = if last { "└── " } else { "├── " };
+            let width = self.width;
+            writeln!(out, "{}{}[{:>width$}]  {}", prefix, branch, size, entry.name)?;
+            let indent = if last { "    " } else { "│   " };
+            self.draw(out, child, &format!("{}{}", prefix, indent))?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn handle_tree_command(
+    client: &Client,
+    args: TreeArgs,
+    renderer: &Renderer,
+) -> Result<()> {
+    let mut entries: Vec<Entry> = client
+        .list_tree(&args.target, args.depth, args.page_size)
+        .try_collect()
+        .await?;
+    if renderer.format() != OutputFormat::Table {
+        return renderer.list(&entries, &args.options);
+    }
+
+    let relative_path = |entry: &Entry| {
+        let path = entry.path();
+        match args.target.path.as_str() {
+            "" => path,
+            base => path
+                .strip_prefix(base)
+                .map_or(path.clone(), |rest| rest.trim_start_matches('/').to_string()),
+        }
+    };
+    // The target itself is drawn as the root, and as its own child it would never end
+    entries.retain(|entry| !relative_path(entry).is_empty());
+    let paths: Vec<String> = entries.iter().map(relative_path).collect();
+    let relative: Vec<&str> = paths.iter().map(String::as_str).collect();
+
+    // Directory sizes count every file below them, also those deeper than the tree goes
+    let files = FileQuery {
+        entry_type: Some(EntryType::File),
+        ..Default::default()
+    };
+    let levels: BTreeSet<u32> = relative
+        .iter()
+        .zip(&entries)
+        .filter(|(_, entry)| entry.is_dir())
+        .map(|(path, _)| path.split('/').count() as u32)
+        .chain([0])
+        .collect();
+    let groups = try_join_all(
+        levels
+            .into_iter()
+            .map(|level| client.query_groups(&args.target, &files, GroupBy::Depth(level))),
+    )
+    .await?;
+    let sizes: HashMap<String, u64> = groups
+        .into_iter()
+        .flatten()
+        .map(|group| {
+            let path = match group.group {
+                Value::String(path) => path,
+                other => other.to_string(),
+            };
+            (path, group.size)
+        })
+        .collect();
+
+    let mut children: HashMap<&str, Vec<(&str, &Entry)>> = HashMap::new();
+    for (path, entry) in relative.iter().zip(&entries) {
+        let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+        children.entry(parent).or_default().push((path, entry));
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+    }
+
+    let mut tree = Tree { children, sizes, width: 0 };
+    let total = tree.sizes.get("").copied().unwrap_or(0);
+    tree.width = relative
+        .iter()
+        .zip(&entries)
+        .map(|(path, entry)| humanize::size(tree.size(path, entry)).len())
+        .chain([humanize::size(total).len()])
+        .max()
+        .unwrap_or(0);
+
+    let mut out = std::io::stdout().lock();
+    writeln!(out, "[{:>width$}]  {}", humanize::size(total), args.target, width = tree.width)?;
+    tree.draw(&mut out, "", "")?;
+    let dirs = entries.iter().filter(|entry| entry.is_dir()).count();
+    let files = entries.len() - dirs;
+    let plural = |n: usize, one: &str, many: &str| {
+        format!("{} {}", n, if n == 1 { one } else { many })
+    };
+    let dirs = plural(dirs, "directory", "directories");
+    writeln!(out, "\n{}, {}", dirs, plural(files, "file", "files"))?;
+    Ok(())
+}
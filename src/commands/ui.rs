@@ -11,10 +11,10 @@ use ratatui::{
     style::{Style, Modifier, Stylize},
 };
 use std::io::stdout;
-use serde_json::Value;
 use strum_macros::Display;
 
-use crate::client::Client;
+use rsf::models::{Volume, VolumeType};
+use rsf::Client;
 
 // Constants for icons (with added space after each icon)
 const WINDOWS_ICON: &str = "\u{f17a} ";    // Windows icon
@@ -59,7 +59,7 @@ impl SelectedTab {
         vec![Self::VolumeShow, Self::Scans, Self::Browse]
     }
 
-    fn to_index(&self) -> usize {
+    fn to_index(self) -> usize {
         match self {
             Self::VolumeShow => 0,
             Self::Scans => 1,
@@ -97,19 +97,15 @@ impl SelectedTab {
 
 struct TableState {
     selected: Option<usize>,
-    items: Vec<Value>,
+    items: Vec<Volume>,
     use_unicode: bool,
     selected_tab: SelectedTab,
 }
 
 impl TableState {
-    fn new(mut items: Vec<Value>) -> Self {
+    fn new(mut items: Vec<Volume>) -> Self {
         // Sort volumes by name
-        items.sort_by(|a, b| {
-            let name_a = a["vol"].as_str().unwrap_or("");
-            let name_b = b["vol"].as_str().unwrap_or("");
-            name_a.cmp(name_b)
-        });
+        items.sort_by(|a, b| a.vol.cmp(&b.vol));
         
         // Test if terminal can display unicode icons
         let use_unicode = String::from(WINDOWS_ICON).chars().all(|c| !c.is_control()) 
@@ -125,19 +121,19 @@ impl TableState {
         }
     }
 
-    fn get_os_icon_with_style(&self, vol_type: &str) -> (String, Style) {
+    fn get_os_icon_with_style(&self, vol_type: Option<&VolumeType>) -> (String, Style) {
         let icon = if self.use_unicode {
-            match vol_type.to_lowercase().as_str() {
-                "windows" => WINDOWS_ICON,
-                "linux" => LINUX_ICON,
-                "virtual" => VIRTUAL_ICON,
+            match vol_type {
+                Some(VolumeType::Windows) => WINDOWS_ICON,
+                Some(VolumeType::Linux) => LINUX_ICON,
+                Some(VolumeType::Virtual) => VIRTUAL_ICON,
                 _ => UNKNOWN_ICON,
             }
         } else {
-            match vol_type.to_lowercase().as_str() {
-                "windows" => WINDOWS_FALLBACK,
-                "linux" => LINUX_FALLBACK,
-                "virtual" => VIRTUAL_FALLBACK,
+            match vol_type {
+                Some(VolumeType::Windows) => WINDOWS_FALLBACK,
+                Some(VolumeType::Linux) => LINUX_FALLBACK,
+                Some(VolumeType::Virtual) => VIRTUAL_FALLBACK,
                 _ => UNKNOWN_FALLBACK,
             }
         };
@@ -175,16 +171,16 @@ impl TableState {
 }
 
 pub async fn handle_ui_command(client: &Client) -> Result<()> {
+    // Get initial volumes data before taking over the terminal, so errors are readable
+    let volumes = client.get_volumes().await?;
+
     // Enable mouse capture when initializing terminal
     stdout().execute(crossterm::event::EnableMouseCapture)?;
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
 
-    // Get initial volumes data
-    let volumes = client.get_volumes().await?;
-    let volumes_array = volumes.as_array().unwrap_or(&vec![]).clone();
-    let mut table_state = TableState::new(volumes_array);
+    let mut table_state = TableState::new(volumes);
     let mut selection_state = ratatui::widgets::TableState::default();
     selection_state.select(table_state.selected);
     
@@ -209,15 +205,13 @@ pub async fn handle_ui_command(client: &Client) -> Result<()> {
             
             // Create table rows with styled OS icons
             let rows: Vec<Row> = table_state.items.iter()
-                .filter_map(|volume| {
-                    let name = volume["vol"].as_str()?;
-                    let vol_type = volume["type"].as_str().unwrap_or("");
-                    let (icon, style) = table_state.get_os_icon_with_style(vol_type);
+                .map(|volume| {
+                    let (icon, style) = table_state.get_os_icon_with_style(volume.vol_type.as_ref());
                     
                     // Create a styled row with the icon and name
-                    Some(Row::new(vec![
-                        format!("{}{}", icon, name)
-                    ]).style(style))
+                    Row::new(vec![
+                        format!("{}{}", icon, volume.vol)
+                    ]).style(style)
                 })
                 .collect();
 
@@ -258,7 +252,9 @@ pub async fn handle_ui_command(client: &Client) -> Result<()> {
                     let details_text = match table_state.selected {
                         Some(index) => {
                             if let Some(volume) = table_state.items.get(index) {
-                                serde_json::to_string_pretty(volume).unwrap_or_else(|_| "Error formatting JSON".to_string())
+                                crate::output::to_value(volume)
+                                    .and_then(|value| Ok(serde_json::to_string_pretty(&value)?))
+                                    .unwrap_or_else(|_| "Error formatting JSON".to_string())
                             } else {
                                 "No volume selected".to_string()
                             }
@@ -334,31 +330,29 @@ pub async fn handle_ui_command(client: &Client) -> Result<()> {
                         _ => {}
                     }
                 }
-                Event::Mouse(MouseEvent { kind, row, column, .. }) => {
-                    if let MouseEventKind::Down(_) = kind {
-                        // Create a Position from the mouse coordinates
-                        let mouse_point = Position { x: column, y: row };
-
-                        // Handle volume list clicks
-                        if volumes_area.contains(mouse_point) {
-                            // Convert to relative position within the volumes area
-                            let relative_row = row.saturating_sub(volumes_area.y + 1); // +1 to account for border
-                            if relative_row < table_state.items.len() as u16 {
-                                table_state.selected = Some(relative_row as usize);
-                                selection_state.select(Some(relative_row as usize));
-                            }
+                Event::Mouse(MouseEvent { kind: MouseEventKind::Down(_), row, column, .. }) => {
+                    // Create a Position from the mouse coordinates
+                    let mouse_point = Position { x: column, y: row };
+
+                    // Handle volume list clicks
+                    if volumes_area.contains(mouse_point) {
+                        // Convert to relative position within the volumes area
+                        let relative_row = row.saturating_sub(volumes_area.y + 1); // +1 to account for border
+                        if relative_row < table_state.items.len() as u16 {
+                            table_state.selected = Some(relative_row as usize);
+                            selection_state.select(Some(relative_row as usize));
                         }
-                        // Handle tab clicks
-                        else if tabs_area.contains(mouse_point) {
-                            // Only handle clicks in the tab header row (right_chunks[0])
-                            if row == tabs_area.y {  // First row of tabs area
-                                // Convert to relative position within the tabs area
-                                let relative_x = column.saturating_sub(tabs_area.x);
-                                let tab_index = relative_x / (TAB_WIDTH + 2);
-                                if tab_index < 3 {  // We have 3 tabs
-                                    if let Some(tab) = SelectedTab::from_index(tab_index as usize) {
-                                        table_state.selected_tab = tab;
-                                    }
+                    }
+                    // Handle tab clicks
+                    else if tabs_area.contains(mouse_point) {
+                        // Only handle clicks in the tab header row (right_chunks[0])
+                        if row == tabs_area.y {  // First row of tabs area
+                            // Convert to relative position within the tabs area
+                            let relative_x = column.saturating_sub(tabs_area.x);
+                            let tab_index = relative_x / (TAB_WIDTH + 2);
+                            if tab_index < 3 {  // We have 3 tabs
+                                if let Some(tab) = SelectedTab::from_index(tab_index as usize) {
+                                    table_state.selected_tab = tab;
                                 }
                             }
                         }
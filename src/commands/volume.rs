@@ -1,54 +1,180 @@
-use anyhow::{Context, Result};
-use clap::Subcommand;
-use crate::client::Client;
+use anyhow::{bail, Result};
+use clap::{Subcommand, ValueEnum};
+use rsf::models::{Scan, ScanType, Volume, VolumeType};
+use rsf::{Client, RsfError};
+use std::collections::BTreeMap;
+
+use super::{confirm, settings_object, Setting};
+use super::scan::last_successful_scan;
+use crate::filter::FilterOptions;
+use crate::humanize;
+use crate::output::{ListOptions, OutputFormat, Renderer, Report};
 
 #[derive(Subcommand)]
 pub enum VolumeCommands {
     /// List all volumes
     List {
-        /// Output full JSON response
-        #[arg(long)]
-        json: bool,
+        #[command(flatten)]
+        filter: FilterOptions,
+        #[command(flatten)]
+        options: ListOptions,
     },
     /// Show details of a specific volume
     Show {
         /// Name of the volume
         name: String,
-        /// Output full JSON response
+    },
+    /// Add a volume
+    Create {
+        /// Name of the new volume
+        name: String,
+        /// Address of the agent that crawls the volume
         #[arg(long)]
-        json: bool,
+        agent: String,
+        /// Path of the volume root on the agent
+        #[arg(long)]
+        root: String,
+        /// Operating system of the volume storage
+        #[arg(long = "type", value_enum, default_value_t = VolumeTypeArg::Linux)]
+        vol_type: VolumeTypeArg,
+    },
+    /// Change settings of a volume
+    Update {
+        /// Name of the volume
+        name: String,
+        /// Field to change, as KEY=VALUE; nested fields use a dot path and values are read
+        /// as JSON when they parse, e.g. roots.agent1=/mnt/data or ignored_dirs='["*.tmp"]'
+        #[arg(long = "set", value_name = "KEY=VALUE", required = true)]
+        settings: Vec<Setting>,
     },
+    /// Delete a volume and everything indexed in it
+    Delete {
+        /// Name of the volume
+        name: String,
+        /// Do not ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum VolumeTypeArg {
+    Linux,
+    Windows,
+    Virtual,
+}
+
+impl From<VolumeTypeArg> for VolumeType {
+    fn from(arg: VolumeTypeArg) -> Self {
+        match arg {
+            VolumeTypeArg::Linux => VolumeType::Linux,
+            VolumeTypeArg::Windows => VolumeType::Windows,
+            VolumeTypeArg::Virtual => VolumeType::Virtual,
+        }
+    }
 }
 
-pub async fn handle_volume_command(client: &Client, command: VolumeCommands) -> Result<()> {
+pub async fn handle_volume_command(
+    client: &Client,
+    command: VolumeCommands,
+    renderer: &Renderer,
+) -> Result<()> {
     match command {
-        VolumeCommands::List { json } => {
-            let response = client.get_volumes().await?;
-            if json {
-                println!("{}", serde_json::to_string_pretty(&response)?);
+        VolumeCommands::List { filter, options } => {
+            // The volume endpoint has no query parameters, so all filtering happens here
+            let volumes = filter.apply(client.get_volumes().await?)?;
+            renderer.list(&volumes, &options)?;
+        }
+        VolumeCommands::Show { name } => {
+            let volume = client
+                .get_volume(&name)
+                .await?
+                .ok_or_else(|| RsfError::NotFound(format!("volume {}", name)))?;
+            if renderer.format() == OutputFormat::Table {
+                // The scan history is extra, so without access to it the report goes without
+                let last_scan = last_successful_scan(client, &volume.vol)
+                    .await
+                    .unwrap_or_else(|err| {
+                        log::info!("Cannot look up the last scan of {}: {:#}", volume.vol, err);
+                        None
+                    });
+                volume_report(&volume, last_scan.as_ref()).print()?;
+            } else {
+                renderer.item(&volume)?;
+            }
+        }
+        VolumeCommands::Create { name, agent, root, vol_type } => {
+            let volume = Volume {
+                vol: name,
+                vol_type: Some(vol_type.into()),
+                default_agent_address: Some(agent.clone()),
+                roots: BTreeMap::from([(agent, root)]),
+                ..Default::default()
+            };
+            let volume = client.create_volume(&volume).await?;
+            if renderer.format() == OutputFormat::Table {
+                println!("Created volume {}", volume.vol);
             } else {
-                let volumes = response.as_array().context("Expected array of volumes")?;
-                for volume in volumes {
-                    if let Some(name) = volume["vol"].as_str() {
-                        println!("{}", name);
-                    }
-                }
+                renderer.item(&volume)?;
             }
         }
-        VolumeCommands::Show { name, json } => {
-            match client.get_volume(&name).await? {
-                Some(volume) => {
-                    if json {
-                        println!("{}", serde_json::to_string_pretty(&volume)?);
-                    } else {
-                        let vol_name = volume["vol"].as_str().unwrap_or("");
-                        let agent = volume["default_agent_address"].as_str().unwrap_or("");
-                        println!("{} {}", vol_name, agent);
-                    }
-                }
-                None => eprintln!("There is no volume {}", name),
+        VolumeCommands::Update { name, settings } => {
+            let volume = client.update_volume(&name, &settings_object(settings)?).await?;
+            if renderer.format() == OutputFormat::Table {
+                println!("Updated volume {}", volume.vol);
+            } else {
+                renderer.item(&volume)?;
             }
         }
+        VolumeCommands::Delete { name, yes } => {
+            if !yes && !confirm(&format!("Delete volume {} and everything indexed in it?", name))? {
+                bail!("Aborted, volume {} was not deleted", name);
+            }
+            client.delete_volume(&name).await?;
+            println!("Deleted volume {}", name);
+        }
     }
     Ok(())
-} 
\ No newline at end of file
+}
+
+fn volume_report(volume: &Volume, last_scan: Option<&Scan>) -> Report {
+    let report = Report::default()
+        .section(format!("Volume {}", volume.vol))
+        .optional("Type", volume.vol_type.as_ref())
+        .optional("ID", volume.id)
+        .optional("Default agent", volume.default_agent_address.as_ref())
+        .optional("Mount options", volume.mount_opts.as_ref())
+        .section("Roots");
+    let report = volume
+        .roots
+        .iter()
+        .fold(report, |report, (agent, path)| report.field(agent, path));
+
+    let report = report
+        .section("Contents")
+        .optional("Total size", volume.total_size.map(humanize::size))
+        .optional("Files", volume.num_files.map(humanize::count))
+        .optional("Directories", volume.num_dirs.map(humanize::count))
+        .section("Last successful scan");
+    let report = match last_scan {
+        Some(scan) => report
+            .field("Scan", &scan.id)
+            .optional("Type", scan.scan_type.as_ref())
+            .optional("Ended", scan.end_time.map(humanize::local_time))
+            .optional("Files", scan.num_files.map(humanize::count))
+            .optional("Directories", scan.num_dirs.map(humanize::count)),
+        None => report.field("", "none"),
+    };
+
+    let report = report.section("Scheduled scans");
+    let report = volume.scheduled_scans.iter().fold(report, |report, schedule| {
+        let scan_type = schedule.scan_type.as_ref().map_or("scan", ScanType::as_str);
+        report.field(scan_type, &schedule.schedule)
+    });
+
+    let report = report.section("Ignore rules");
+    volume
+        .ignored_dirs
+        .iter()
+        .fold(report, |report, pattern| report.field("", pattern))
+}
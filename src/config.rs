@@ -0,0 +1,245 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, RsfError};
+
+const CONFIG_DIR_NAME: &str = "rsf";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Contents of `~/.config/rsf/config.toml`.
+///
+/// ```toml
+/// default_profile = "dogfood"
+///
+/// [profiles.dogfood]
+/// url = "https://sf-dogfood/api"
+/// username = "starfish"
+/// password = "starfish"
+/// insecure = true
+///
+/// [profiles.production]
+/// url = "https://starfish.example.com/api"
+/// username = "admin"
+/// ca_bundle = "/etc/ssl/certs/internal-ca.pem"
+/// client_cert = "~/.config/rsf/admin.crt"
+/// client_key = "~/.config/rsf/admin.key"
+/// output = "json"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Profile used when neither `--profile` nor `RSF_PROFILE` is given
+    pub default_profile: Option<String>,
+    /// Profiles by name
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// File the config was read from, or would be read from if it existed
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+/// Connection settings for a single Starfish server.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Name of the profile in the config file; filled in when resolving
+    #[serde(skip)]
+    pub name: String,
+    /// Base URL of the Starfish API, e.g. `https://sf-dogfood/api`
+    pub url: Option<String>,
+    /// User to log in as
+    pub username: Option<String>,
+    /// Password exchanged for an API token; prompted for if missing
+    pub password: Option<String>,
+    /// PEM file with additional CA certificates to trust; `~/` means the home directory
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM client certificate for mutual TLS; may also contain the private key
+    pub client_cert: Option<PathBuf>,
+    /// PEM (PKCS#8) private key for `client_cert`, if not in the same file
+    pub client_key: Option<PathBuf>,
+    /// Skip TLS certificate verification
+    #[serde(default)]
+    pub insecure: bool,
+    /// Default output format: table, json, yaml, csv or ndjson
+    pub output: Option<String>,
+    /// Seconds to wait for a connection to the server [default: 10]
+    pub connect_timeout: Option<u64>,
+    /// Seconds to wait for a complete response [default: 60]
+    pub read_timeout: Option<u64>,
+    /// How often to retry GET requests that failed transiently [default: 3]
+    pub max_retries: Option<u32>,
+}
+
+impl Config {
+    /// Loads the config file at `path`, or at [`default_config_path`] if none is given.
+    ///
+    /// A missing default file gives an empty config; a missing explicit file is an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_config_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && !explicit => {
+                return Ok(Self {
+                    path: Some(path),
+                    ..Self::default()
+                })
+            }
+            Err(err) => {
+                return Err(RsfError::Config(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    err
+                )))
+            }
+        };
+
+        let config: Self = toml::from_str(&contents).map_err(|err| {
+            RsfError::Config(format!("Failed to parse {}: {}", path.display(), err))
+        })?;
+        Ok(Self {
+            path: Some(path),
+            ..config
+        })
+    }
+
+    /// Picks the profile to use and applies `RSF_*` environment overrides on top of it.
+    ///
+    /// An explicitly requested profile must exist. Otherwise `default_profile` is used,
+    /// falling back to the only profile in the file, or to an empty profile that has to be
+    /// completed from the environment.
+    pub fn resolve_profile(&self, requested: Option<&str>) -> Result<Profile> {
+        let name = match requested {
+            Some(name) => {
+                if !self.profiles.contains_key(name) {
+                    return Err(RsfError::Config(format!(
+                        "Unknown profile '{}' (available: {})",
+                        name,
+                        self.profile_names()
+                    )));
+                }
+                Some(name.to_string())
+            }
+            None => match &self.default_profile {
+                Some(name) => Some(name.clone()),
+                None if self.profiles.len() == 1 => self.profiles.keys().next().cloned(),
+                None => None,
+            },
+        };
+
+        let mut profile = match &name {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .cloned()
+                .ok_or_else(|| {
+                    RsfError::Config(format!("default_profile '{}' is not defined", name))
+                })?,
+            None => Profile::default(),
+        };
+        profile.name = name.unwrap_or_else(|| "default".to_string());
+        profile.apply_env_overrides();
+        profile.expand_home();
+
+        if profile.url.is_none() {
+            return Err(RsfError::Config(format!(
+                "No Starfish URL configured for profile '{}'. Add it to {} or set RSF_URL",
+                profile.name,
+                self.path
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| CONFIG_FILE_NAME.to_string())
+            )));
+        }
+
+        Ok(profile)
+    }
+
+    fn profile_names(&self) -> String {
+        if self.profiles.is_empty() {
+            "none".to_string()
+        } else {
+            self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+        }
+    }
+}
+
+impl Profile {
+    fn apply_env_overrides(&mut self) {
+        if let Some(url) = env_var("RSF_URL") {
+            self.url = Some(url);
+        }
+        if let Some(username) = env_var("RSF_USERNAME") {
+            self.username = Some(username);
+        }
+        if let Some(password) = env_var("RSF_PASSWORD") {
+            self.password = Some(password);
+        }
+        if let Some(ca_bundle) = env_var("RSF_CA_BUNDLE") {
+            self.ca_bundle = Some(PathBuf::from(ca_bundle));
+        }
+        if let Some(client_cert) = env_var("RSF_CLIENT_CERT") {
+            self.client_cert = Some(PathBuf::from(client_cert));
+        }
+        if let Some(client_key) = env_var("RSF_CLIENT_KEY") {
+            self.client_key = Some(PathBuf::from(client_key));
+        }
+        if let Some(insecure) = env_var("RSF_INSECURE") {
+            self.insecure = matches!(insecure.as_str(), "1" | "true" | "yes");
+        }
+        if let Some(output) = env_var("RSF_OUTPUT") {
+            self.output = Some(output);
+        }
+    }
+
+    /// Resolves a leading `~/` in the file paths against the home directory.
+    fn expand_home(&mut self) {
+        for path in [&mut self.ca_bundle, &mut self.client_cert, &mut self.client_key]
+            .into_iter()
+            .flatten()
+        {
+            if let (Ok(rest), Some(home)) = (path.strip_prefix("~"), dirs::home_dir()) {
+                *path = home.join(rest);
+            }
+        }
+    }
+
+    /// Base URL without a trailing slash, so paths can be appended with `format!`.
+    pub fn base_url(&self) -> &str {
+        self.url.as_deref().unwrap_or_default().trim_end_matches('/')
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// `$XDG_CONFIG_HOME/rsf`, falling back to `~/.config/rsf`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env_var("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir()?.join(".config"),
+    };
+    Some(base.join(CONFIG_DIR_NAME))
+}
+
+/// `$XDG_CACHE_HOME/rsf`, falling back to `~/.cache/rsf`.
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match env_var("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir()?.join(".cache"),
+    };
+    Some(base.join(CONFIG_DIR_NAME))
+}
+
+/// `config.toml` in [`config_dir`].
+pub fn default_config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join(CONFIG_FILE_NAME))
+}
@@ -0,0 +1,54 @@
+use reqwest::StatusCode;
+
+/// Errors returned by [`Client`](crate::client::Client) methods.
+#[derive(Debug, thiserror::Error)]
+pub enum RsfError {
+    /// Missing or rejected credentials (HTTP 401)
+    #[error("Not authorized")]
+    Unauthorized,
+    /// Credentials are valid but lack permission (HTTP 403)
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    /// The requested resource does not exist (HTTP 404)
+    #[error("Not found: {0}")]
+    NotFound(String),
+    /// Any other unsuccessful response, with the response body
+    #[error("Server returned {0}: {1}")]
+    ServerError(StatusCode, String),
+    /// The request could not be sent or the response could not be read
+    #[error("Request failed")]
+    Transport(#[from] reqwest::Error),
+    /// The response body is not what the API promises
+    #[error("Unexpected response from server")]
+    Decode(#[from] serde_json::Error),
+    /// Invalid client settings, e.g. an unreadable CA bundle
+    #[error("{0}")]
+    Config(String),
+}
+
+pub type Result<T, E = RsfError> = std::result::Result<T, E>;
+
+impl RsfError {
+    /// Error for an unsuccessful response to a request for `path`.
+    pub fn from_status(status: StatusCode, path: &str, body: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => Self::Unauthorized,
+            StatusCode::FORBIDDEN => Self::Forbidden(path.to_string()),
+            StatusCode::NOT_FOUND => Self::NotFound(path.to_string()),
+            _ => Self::ServerError(status, body),
+        }
+    }
+
+    /// Process exit code used by the CLI for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Unauthorized => 3,
+            Self::Forbidden(_) => 4,
+            Self::NotFound(_) => 5,
+            Self::ServerError(..) => 6,
+            Self::Transport(_) => 7,
+            Self::Decode(_) => 8,
+            Self::Config(_) => 9,
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::{anyhow, bail, Result};
+use clap::Args;
+use futures::{future, Stream, StreamExt, TryStreamExt};
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use crate::output::{cell, check_columns, lookup, to_value, Tabular};
+
+/// Filtering and ordering options shared by list commands.
+#[derive(Args, Default)]
+pub struct FilterOptions {
+    /// Keep items matching FIELD=VALUE, FIELD!=VALUE, FIELD~REGEX, FIELD!~REGEX or a
+    /// numeric comparison such as FIELD>N; repeat to combine filters
+    #[arg(long = "filter", value_name = "EXPR")]
+    pub filters: Vec<Filter>,
+    /// Field to sort by; nested fields use a dot path
+    #[arg(long, value_name = "FIELD")]
+    pub sort_by: Option<String>,
+    /// Reverse the order
+    #[arg(long)]
+    pub reverse: bool,
+    /// Show at most this many items
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Filter {
+    pub field: String,
+    pub op: FilterOp,
+}
+
+#[derive(Clone, Debug)]
+pub enum FilterOp {
+    Eq(String),
+    NotEq(String),
+    Matches(Regex),
+    NotMatches(Regex),
+    /// Numeric comparison; `or_equal` turns `>` into `>=`
+    Compare {
+        ordering: Ordering,
+        or_equal: bool,
+        value: f64,
+    },
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        // Longest operators first, so `!=` is not read as `=`
+        const OPERATORS: [&str; 8] = ["!~", "!=", ">=", "<=", "~", "=", ">", "<"];
+        let (pos, op) = expr
+            .char_indices()
+            .find_map(|(pos, _)| {
+                OPERATORS
+                    .iter()
+                    .find(|op| expr[pos..].starts_with(*op))
+                    .map(|op| (pos, *op))
+            })
+            .ok_or_else(|| anyhow!("Invalid filter '{}', expected e.g. type=linux", expr))?;
+
+        let field = expr[..pos].trim().to_string();
+        let value = &expr[pos + op.len()..];
+        if field.is_empty() {
+            bail!("Invalid filter '{}', missing field name", expr);
+        }
+
+        let number = || {
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| anyhow!("Filter '{}' compares with a non-numeric value", expr))
+        };
+        let op = match op {
+            "=" => FilterOp::Eq(value.to_string()),
+            "!=" => FilterOp::NotEq(value.to_string()),
+            "~" => FilterOp::Matches(Regex::new(value)?),
+            "!~" => FilterOp::NotMatches(Regex::new(value)?),
+            ">" => FilterOp::Compare {
+                ordering: Ordering::Greater,
+                or_equal: false,
+                value: number()?,
+            },
+            ">=" => FilterOp::Compare {
+                ordering: Ordering::Greater,
+                or_equal: true,
+                value: number()?,
+            },
+            "<" => FilterOp::Compare {
+                ordering: Ordering::Less,
+                or_equal: false,
+                value: number()?,
+            },
+            "<=" => FilterOp::Compare {
+                ordering: Ordering::Less,
+                or_equal: true,
+                value: number()?,
+            },
+            _ => unreachable!(),
+        };
+        Ok(Self { field, op })
+    }
+}
+
+impl Filter {
+    fn matches(&self, item: &Value) -> bool {
+        let value = lookup(item, &self.field);
+        let text = value.map(cell).unwrap_or_default();
+        match &self.op {
+            FilterOp::Eq(expected) => value.is_some() && text == *expected,
+            FilterOp::NotEq(expected) => value.is_none() || text != *expected,
+            FilterOp::Matches(regex) => value.is_some() && regex.is_match(&text),
+            FilterOp::NotMatches(regex) => value.is_none() || !regex.is_match(&text),
+            FilterOp::Compare { ordering, or_equal, value: expected } => {
+                match value.and_then(value_number) {
+                    Some(actual) => match actual.partial_cmp(expected) {
+                        Some(Ordering::Equal) => *or_equal,
+                        Some(result) => result == *ordering,
+                        None => false,
+                    },
+                    None => false,
+                }
+            }
+        }
+    }
+
+    /// The value this filter requires for its field, if it is a plain equality.
+    pub fn equals(&self) -> Option<&str> {
+        match &self.op {
+            FilterOp::Eq(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FilterOptions {
+    /// Whether the options sort, so items cannot be passed on before all have arrived.
+    pub fn needs_all_items(&self) -> bool {
+        self.sort_by.is_some() || self.reverse
+    }
+
+    /// Filters and limits items as they arrive; sorting needs [`FilterOptions::apply`].
+    ///
+    /// Filter fields are checked against the first item, as `apply` checks them against
+    /// all items.
+    pub fn apply_stream<'a, T: Tabular + 'a>(
+        &'a self,
+        items: impl Stream<Item = Result<T>> + 'a,
+    ) -> impl Stream<Item = Result<T>> + 'a {
+        let mut checked = false;
+        items
+            .try_filter_map(move |item| {
+                let keep = to_value(&item).and_then(|value| {
+                    if !checked {
+                        let fields = self.filters.iter().map(|filter| filter.field.as_str());
+                        check_columns::<T>(fields, std::slice::from_ref(&value))?;
+                        checked = true;
+                    }
+                    Ok(self.filters.iter().all(|filter| filter.matches(&value)))
+                });
+                future::ready(keep.map(|keep| keep.then_some(item)))
+            })
+            .take(self.limit.unwrap_or(usize::MAX))
+    }
+
+    /// Filters, sorts and limits `items` on the client.
+    pub fn apply<T: Tabular>(&self, items: Vec<T>) -> Result<Vec<T>> {
+        let values = items.iter().map(to_value).collect::<Result<Vec<_>>>()?;
+        check_columns::<T>(
+            self.filters
+                .iter()
+                .map(|filter| filter.field.as_str())
+                .chain(self.sort_by.as_deref()),
+            &values,
+        )?;
+
+        let mut selected: Vec<(T, Value)> = items
+            .into_iter()
+            .zip(values)
+            .filter(|(_, value)| self.filters.iter().all(|filter| filter.matches(value)))
+            .collect();
+
+        if let Some(sort_by) = &self.sort_by {
+            selected.sort_by(|(_, a), (_, b)| compare(lookup(a, sort_by), lookup(b, sort_by)));
+        }
+        if self.reverse {
+            selected.reverse();
+        }
+        if let Some(limit) = self.limit {
+            selected.truncate(limit);
+        }
+        Ok(selected.into_iter().map(|(item, _)| item).collect())
+    }
+}
+
+/// Orders numbers numerically and everything else as text, with missing values last.
+pub fn compare(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a, b) {
+        (None | Some(Value::Null), None | Some(Value::Null)) => Ordering::Equal,
+        (None | Some(Value::Null), _) => Ordering::Greater,
+        (_, None | Some(Value::Null)) => Ordering::Less,
+        (Some(a), Some(b)) => match (value_number(a), value_number(b)) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => cell(a).cmp(&cell(b)),
+        },
+    }
+}
+
+fn value_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn filter(expr: &str) -> Filter {
+        expr.parse().unwrap()
+    }
+
+    fn volume() -> Value {
+        json!({
+            "vol": "proj",
+            "type": "linux",
+            "mount_opts": null,
+            "total_size": 1024,
+            "roots": {"agent1": "/mnt/proj"},
+            "tags": ["hot", "large"],
+        })
+    }
+
+    #[test]
+    fn longer_operators_take_precedence() {
+        assert!(matches!(filter("type!=linux").op, FilterOp::NotEq(ref v) if v == "linux"));
+        assert!(matches!(filter("vol!~^p").op, FilterOp::NotMatches(_)));
+        assert!(matches!(
+            filter("total_size>=10").op,
+            FilterOp::Compare { ordering: Ordering::Greater, or_equal: true, .. }
+        ));
+        assert!(matches!(
+            filter("total_size<10").op,
+            FilterOp::Compare { ordering: Ordering::Less, or_equal: false, .. }
+        ));
+    }
+
+    #[test]
+    fn the_first_operator_splits_field_and_value() {
+        let eq = filter("name=a>b");
+        assert_eq!(eq.field, "name");
+        assert!(matches!(eq.op, FilterOp::Eq(ref v) if v == "a>b"));
+
+        let spaced = filter(" vol = proj");
+        assert_eq!(spaced.field, "vol");
+        assert_eq!(spaced.equals(), Some(" proj"));
+    }
+
+    #[test]
+    fn rejects_invalid_filters() {
+        assert!("vol".parse::<Filter>().is_err());
+        assert!("=proj".parse::<Filter>().is_err());
+        assert!("total_size>big".parse::<Filter>().is_err());
+        assert!("vol~(".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn matches_fields_and_dot_paths() {
+        let volume = volume();
+        assert!(filter("vol=proj").matches(&volume));
+        assert!(!filter("vol=home").matches(&volume));
+        assert!(filter("roots.agent1~^/mnt").matches(&volume));
+        assert!(filter("tags.1=large").matches(&volume));
+        assert!(filter("total_size>1000").matches(&volume));
+        assert!(filter("total_size<=1024").matches(&volume));
+        assert!(!filter("total_size<1024").matches(&volume));
+        assert!(!filter("type>1").matches(&volume));
+    }
+
+    #[test]
+    fn missing_fields_only_match_negations() {
+        let volume = volume();
+        for expr in ["missing=x", "missing~.", "missing>0", "missing<0", "roots.agent2=x"] {
+            assert!(!filter(expr).matches(&volume), "{}", expr);
+        }
+        for expr in ["missing!=x", "missing!~.", "roots.agent2!=x"] {
+            assert!(filter(expr).matches(&volume), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn null_values_read_as_empty() {
+        let volume = volume();
+        assert!(filter("mount_opts!=ro").matches(&volume));
+        assert!(filter("mount_opts=").matches(&volume));
+        assert!(!filter("mount_opts~.").matches(&volume));
+    }
+}
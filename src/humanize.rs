@@ -0,0 +1,102 @@
+//! Formatting of counts, rates and durations for people rather than scripts.
+
+use chrono::{DateTime, Local};
+use rsf::models::EntryType;
+use serde::{Serialize, Serializer};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Current time in seconds since the Unix epoch, as used by API timestamps.
+pub fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// Count with thousands separators, e.g. `1,234,567`.
+pub fn count(n: u64) -> String {
+    let digits = n.to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Size in bytes with a binary unit, e.g. `1.5 GiB`.
+pub fn size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Size in bytes for list output, written with a unit when `human` is set.
+pub struct Size {
+    pub bytes: u64,
+    pub human: bool,
+}
+
+impl Serialize for Size {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.human {
+            serializer.serialize_str(&size(self.bytes))
+        } else {
+            serializer.serialize_u64(self.bytes)
+        }
+    }
+}
+
+/// Duration rounded to whole seconds, e.g. `1h 5m 3s`.
+pub fn duration(d: Duration) -> String {
+    humantime::format_duration(Duration::from_secs(d.as_secs_f64().round() as u64)).to_string()
+}
+
+/// Items per second, e.g. `1,250 files/s`, with a decimal for slow rates such as
+/// `0.4 files/s`.
+pub fn rate(per_sec: f64, unit: &str) -> String {
+    if per_sec < 10.0 {
+        return format!("{:.1} {}/s", per_sec, unit);
+    }
+    format!("{} {}/s", count(per_sec.round() as u64), unit)
+}
+
+/// API timestamp in seconds since the Unix epoch, in the local timezone.
+pub fn local_time(secs: f64) -> String {
+    let nanos = (secs.fract() * 1e9) as u32;
+    match DateTime::from_timestamp(secs.trunc() as i64, nanos) {
+        Some(time) => time
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string(),
+        None => secs.to_string(),
+    }
+}
+
+/// Type and permission bits as shown by `ls -l`, e.g. `drwxr-xr-x`.
+pub fn mode(entry_type: Option<&EntryType>, mode: Option<u32>) -> String {
+    let kind = match entry_type {
+        Some(EntryType::Directory) => 'd',
+        Some(EntryType::Link) => 'l',
+        Some(EntryType::File) => '-',
+        _ => '?',
+    };
+    let Some(mode) = mode else {
+        return format!("{}?????????", kind);
+    };
+    let mut result = String::from(kind);
+    for (i, c) in "rwxrwxrwx".chars().enumerate() {
+        result.push(if mode & (1 << (8 - i)) != 0 { c } else { '-' });
+    }
+    result
+}
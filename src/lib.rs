@@ -0,0 +1,29 @@
+//! Client library for the [Starfish](https://starfishstorage.com) REST API.
+//!
+//! This is the code behind the `rsf` command line tool, usable from other Rust programs:
+//!
+//! ```no_run
+//! use rsf::{Auth, Client, Config};
+//!
+//! # async fn example() -> rsf::Result<()> {
+//! // Same profiles as `rsf --profile production`
+//! let profile = Config::load(None)?.resolve_profile(Some("production"))?;
+//! let client = Client::new(&profile, &Auth::None)?;
+//! let token = client.login("admin", "secret").await?;
+//!
+//! let client = Client::new(&profile, &Auth::Bearer(token.token))?;
+//! for volume in client.get_volumes().await? {
+//!     println!("{} {:?}", volume.vol, volume.default_agent_address);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod client;
+pub mod config;
+pub mod error;
+pub mod models;
+
+pub use client::{Auth, Client, FileQuery, GroupBy, RawResponse, ScanQuery, SessionToken};
+pub use config::{Config, Profile};
+pub use error::{Result, RsfError};
@@ -1,12 +1,18 @@
-mod client;
+mod auth;
 mod commands;
+mod filter;
+mod humanize;
+mod manifest;
+mod output;
 
 use anyhow::Result;
 use clap::{Parser, CommandFactory};
 use clap_complete::{generate, Generator, Shell};
 use std::io;
-use commands::Commands;
-use client::Client;
+use std::path::PathBuf;
+use commands::{CommandFailed, Commands};
+use output::{OutputFormat, Renderer};
+use rsf::{Client, Config, RsfError};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -15,13 +21,112 @@ struct Cli {
     #[arg(long = "generate", value_enum)]
     generator: Option<Shell>,
 
+    /// Server profile from the config file to use
+    #[arg(long, global = true, env = "RSF_PROFILE")]
+    profile: Option<String>,
+
+    /// Path to the config file [default: ~/.config/rsf/config.toml]
+    #[arg(long, global = true, env = "RSF_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Output format [default: from the profile, else table]
+    #[arg(short, long, global = true, value_enum)]
+    output: Option<OutputFormat>,
+
+    /// Shorthand for --output json
+    #[arg(long, global = true, conflicts_with = "output")]
+    json: bool,
+
+    /// PEM file with CA certificates to trust, overriding the profile's ca_bundle
+    #[arg(long, global = true, value_name = "PEM")]
+    ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate for mutual TLS
+    #[arg(long, global = true, value_name = "PEM")]
+    client_cert: Option<PathBuf>,
+
+    /// PEM private key for --client-cert, if not in the certificate file
+    #[arg(long, global = true, value_name = "PEM", requires = "client_cert")]
+    client_key: Option<PathBuf>,
+
+    /// Skip TLS certificate verification (unsafe, for test servers only)
+    #[arg(long, global = true)]
+    insecure: bool,
+
+    /// Log more details to stderr (-v for retries, -vv for every request)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+/// Exit code for errors that do not come from the Starfish client
+const EXIT_FAILURE: i32 = 1;
+
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+async fn main() {
+    if let Err(err) = run(Cli::parse()).await {
+        // Output piped into e.g. `head` that exited early
+        if err
+            .downcast_ref::<io::Error>()
+            .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe)
+        {
+            return;
+        }
+        eprintln!("Error: {}", error_message(&err));
+        let rsf_error = err.downcast_ref::<RsfError>();
+        if let Some(hint) = rsf_error.and_then(hint) {
+            eprintln!("Hint: {}", hint);
+        }
+        let code = match (rsf_error, err.downcast_ref::<CommandFailed>()) {
+            (Some(err), _) => err.exit_code(),
+            (None, Some(failed)) => failed.code,
+            (None, None) => EXIT_FAILURE,
+        };
+        std::process::exit(code);
+    }
+}
+
+/// Error message followed by its causes. Causes that an error already includes in its own
+/// message are left out, as reqwest errors repeat their whole chain.
+fn error_message(err: &anyhow::Error) -> String {
+    let mut message = String::new();
+    for cause in err.chain() {
+        let text = cause.to_string();
+        if message.contains(&text) {
+            continue;
+        }
+        if !message.is_empty() {
+            message.push_str(": ");
+        }
+        message.push_str(&text);
+    }
+    message
+}
+
+/// Suggestion printed after a client error message.
+fn hint(err: &RsfError) -> Option<&'static str> {
+    match err {
+        RsfError::Unauthorized => {
+            Some("Log in with `rsf login` or check the credentials in your profile")
+        }
+        RsfError::Forbidden(_) => Some("Your Starfish user lacks permission for this operation"),
+        RsfError::NotFound(_) => None,
+        RsfError::ServerError(status, _) if status.is_server_error() => {
+            Some("The Starfish server failed to handle the request, try again later")
+        }
+        RsfError::ServerError(..) => None,
+        RsfError::Transport(_) => {
+            Some("Check the profile URL and that the Starfish server is reachable")
+        }
+        RsfError::Decode(_) => Some("The server may be running an incompatible Starfish version"),
+        RsfError::Config(_) => Some("Check your rsf config file and environment variables"),
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    init_logging(cli.verbose);
 
     if let Some(generator) = cli.generator {
         generate_completion(generator, &mut io::stdout());
@@ -32,23 +137,87 @@ async fn main() -> Result<()> {
         anyhow::anyhow!("A subcommand is required unless using --generate")
     })?;
 
-    let client = Client::new();
+    let config = Config::load(cli.config.as_deref())?;
+    let mut profile = config.resolve_profile(cli.profile.as_deref())?;
+    if let Some(ca_cert) = cli.ca_cert {
+        profile.ca_bundle = Some(ca_cert);
+    }
+    if let Some(client_cert) = cli.client_cert {
+        profile.client_cert = Some(client_cert);
+        profile.client_key = cli.client_key;
+    }
+    if cli.insecure {
+        profile.insecure = true;
+    }
+    if profile.insecure {
+        eprintln!(
+            "Warning: TLS certificate verification is disabled for {}, \
+             the connection is open to interception",
+            profile.base_url()
+        );
+    }
+
+    let command = match command {
+        Commands::Login(args) => return commands::auth::handle_login_command(&profile, args).await,
+        Commands::Logout => return commands::auth::handle_logout_command(&profile).await,
+        command => command,
+    };
+
+    let auth = auth::authenticate(&profile).await?;
+    let client = Client::new(&profile, &auth)?;
+    let format = match (cli.json, cli.output, &profile.output) {
+        (true, _, _) => OutputFormat::Json,
+        (false, Some(format), _) => format,
+        (false, None, Some(name)) => OutputFormat::from_name(name)?,
+        (false, None, None) => OutputFormat::default(),
+    };
+    let renderer = Renderer::new(format);
 
     match command {
         Commands::Volume { command } => {
-            commands::volume::handle_volume_command(&client, command).await?
+            commands::volume::handle_volume_command(&client, command, &renderer).await?
         }
         Commands::Scan { command } => {
-            commands::scan::handle_scan_command(&client, command).await?
+            commands::scan::handle_scan_command(&client, command, &renderer).await?
         }
         Commands::Ui => {
             commands::ui::handle_ui_command(&client).await?
         }
+        Commands::Apply(args) => commands::apply::handle_apply_command(&client, args).await?,
+        Commands::Export(args) => commands::export::handle_export_command(&client, args).await?,
+        Commands::Api(args) => commands::api::handle_api_command(&client, args).await?,
+        Commands::Query(args) => {
+            commands::query::handle_query_command(&client, *args, &renderer).await?
+        }
+        Commands::Du(args) => commands::du::handle_du_command(&client, args, &renderer).await?,
+        Commands::Ls(args) => commands::ls::handle_ls_command(&client, args, &renderer).await?,
+        Commands::Stat(args) => {
+            commands::stat::handle_stat_command(&client, args, &renderer).await?
+        }
+        Commands::Tree(args) => {
+            commands::tree::handle_tree_command(&client, args, &renderer).await?
+        }
+        Commands::Login(_) | Commands::Logout => unreachable!("handled before authenticating"),
     }
 
     Ok(())
 }
 
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        2 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_module("rsf", level)
+        .format_timestamp(None)
+        .format_target(false)
+        .parse_env("RSF_LOG")
+        .init();
+}
+
 fn generate_completion<G: Generator>(gen: G, buf: &mut dyn io::Write) {
     generate(gen, &mut Cli::command(), "rsf", buf);
 }
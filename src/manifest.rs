@@ -0,0 +1,133 @@
+//! Volume configuration kept in a YAML or TOML file, written by `export` and read by
+//! `apply`.
+//!
+//! ```yaml
+//! volumes:
+//!   - vol: proj
+//!     type: linux
+//!     default_agent_address: agent1:30002
+//!     roots:
+//!       agent1:30002: /mnt/proj
+//!     ignored_dirs: [".snapshot"]
+//! ```
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use rsf::models::Volume;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// Volume fields the server fills in, which a manifest cannot change.
+pub const READ_ONLY_FIELDS: &[&str] = &["id", "total_size", "num_files", "num_dirs"];
+
+/// Scan schedule fields the server fills in.
+const SCHEDULE_READ_ONLY_FIELDS: &[&str] = &["id"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ManifestFormat {
+    #[default]
+    Yaml,
+    Toml,
+}
+
+/// Desired configuration of a Starfish instance.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub volumes: Vec<Volume>,
+}
+
+impl Manifest {
+    /// Builds a manifest from volumes as returned by the server, leaving out the fields
+    /// the server fills in.
+    pub fn from_volumes(volumes: Vec<Volume>) -> Result<Self> {
+        let volumes = volumes
+            .into_iter()
+            .map(|volume| {
+                let fields = manifest_fields(&volume)?;
+                serde_json::from_value(Value::Object(fields))
+                    .with_context(|| format!("Failed to export volume {}", volume.vol))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { volumes })
+    }
+
+    pub fn to_string(&self, format: ManifestFormat) -> Result<String> {
+        Ok(match format {
+            ManifestFormat::Yaml => serde_yaml::to_string(self)?,
+            ManifestFormat::Toml => toml::to_string(self).or_else(|err| {
+                // Say which volume TOML cannot hold, if it is down to one of them
+                let volume = self
+                    .volumes
+                    .iter()
+                    .find(|volume| toml::Value::try_from(volume).is_err());
+                match volume {
+                    Some(volume) => Err(err)
+                        .with_context(|| format!("Failed to write volume {} as TOML", volume.vol)),
+                    None => Err(err.into()),
+                }
+            })?,
+        })
+    }
+
+    /// Reads a manifest, picking YAML or TOML by the file extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let manifest: Self = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&contents)
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+            Some("toml") => toml::from_str(&contents)
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+            _ => bail!(
+                "Unknown manifest format for {}, expected a .yaml, .yml or .toml file",
+                path.display()
+            ),
+        };
+
+        for (i, volume) in manifest.volumes.iter().enumerate() {
+            if manifest.volumes[..i].iter().any(|other| other.vol == volume.vol) {
+                bail!("Volume {} is listed twice in {}", volume.vol, path.display());
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// Fields of a volume as a manifest lists them: without the fields the server fills in,
+/// also those of its scan schedules, and without nulls.
+pub fn manifest_fields(volume: &Volume) -> Result<Map<String, Value>> {
+    let Value::Object(mut fields) = serde_json::to_value(volume)? else {
+        unreachable!("volumes serialize to objects");
+    };
+    fields.retain(|key, _| !READ_ONLY_FIELDS.contains(&key.as_str()));
+    if let Some(Value::Array(schedules)) = fields.get_mut("scheduled_scans") {
+        for schedule in schedules.iter_mut().filter_map(Value::as_object_mut) {
+            schedule.retain(|key, _| !SCHEDULE_READ_ONLY_FIELDS.contains(&key.as_str()));
+        }
+    }
+    remove_nulls(&mut fields);
+    Ok(fields)
+}
+
+/// Drops null values at any depth. TOML has no null, and a null says nothing a missing
+/// field does not.
+fn remove_nulls(fields: &mut Map<String, Value>) {
+    fields.retain(|_, value| !value.is_null());
+    for value in fields.values_mut() {
+        remove_nested_nulls(value);
+    }
+}
+
+fn remove_nested_nulls(value: &mut Value) {
+    match value {
+        Value::Object(fields) => remove_nulls(fields),
+        Value::Array(items) => {
+            items.retain(|item| !item.is_null());
+            items.iter_mut().for_each(remove_nested_nulls);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,415 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Defines a string-valued API enum that keeps values this version does not know about,
+/// so they survive a round trip through the typed model.
+macro_rules! api_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// Value not known to this version of rsf
+            Other(String),
+        }
+
+        impl $name {
+            /// Value as sent by the API.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $value,)*
+                    Self::Other(value) => value,
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                match value.to_lowercase().as_str() {
+                    $($value => Self::$variant,)*
+                    _ => Self::Other(value),
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.as_str().to_string()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+/// Models that keep the JSON payload they were read from, so output can show exactly
+/// what the server sent.
+pub trait Payload: DeserializeOwned {
+    /// Payload as sent by the server; `None` for models built on the client.
+    fn payload(&self) -> Option<&Value>;
+
+    /// Reads the model from `payload`, keeping a copy of it.
+    fn from_payload(payload: Value) -> serde_json::Result<Self>;
+}
+
+macro_rules! impl_payload {
+    ($($name:ident),*) => {
+        $(
+            impl Payload for $name {
+                fn payload(&self) -> Option<&Value> {
+                    self.payload.as_ref()
+                }
+
+                fn from_payload(payload: Value) -> serde_json::Result<Self> {
+                    let mut model: Self = serde_json::from_value(payload.clone())?;
+                    model.payload = Some(payload);
+                    Ok(model)
+                }
+            }
+        )*
+    };
+}
+
+impl_payload!(Volume, Scan, Entry);
+
+api_enum! {
+    /// Operating system of the storage behind a volume.
+    pub enum VolumeType {
+        Linux => "linux",
+        Windows => "windows",
+        Virtual => "virtual",
+    }
+}
+
+api_enum! {
+    /// Kind of crawl performed by a scan.
+    pub enum ScanType {
+        Diff => "diff",
+        Mtime => "mtime",
+        Sync => "sync",
+    }
+}
+
+api_enum! {
+    /// Lifecycle state of a scan.
+    pub enum ScanState {
+        Pending => "pending",
+        Running => "running",
+        Pausing => "pausing",
+        Paused => "paused",
+        Stopping => "stopping",
+        Stopped => "stopped",
+        Done => "done",
+        Failed => "failed",
+        Cancelled => "cancelled",
+    }
+}
+
+api_enum! {
+    /// Kind of filesystem entry in the metadata index.
+    pub enum EntryType {
+        File => "file",
+        Directory => "dir",
+        Link => "link",
+    }
+}
+
+impl ScanState {
+    /// Whether the scan can no longer change state.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Stopped | Self::Done | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// A Starfish volume, as returned by `GET /volume/`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Volume {
+    /// Volume name
+    pub vol: String,
+    /// Numeric id assigned by the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    /// Operating system of the volume storage
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub vol_type: Option<VolumeType>,
+    /// Agent that crawls the volume unless a scan asks for another one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_agent_address: Option<String>,
+    /// Path of the volume root on each agent, keyed by agent address
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub roots: BTreeMap<String, String>,
+    /// Options the agents mount the volume storage with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount_opts: Option<String>,
+    /// Directory name patterns the crawler skips
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignored_dirs: Vec<String>,
+    /// Scans the server starts on a schedule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scheduled_scans: Vec<ScanSchedule>,
+    /// Size of all files in bytes, as of the last scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    /// Number of files, as of the last scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_files: Option<u64>,
+    /// Number of directories, as of the last scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_dirs: Option<u64>,
+    /// Fields not covered by the typed model
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+    /// Payload the model was read from, as sent by the server
+    #[serde(skip)]
+    pub payload: Option<Value>,
+}
+
+/// A scan started by the server on a cron schedule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanSchedule {
+    /// Kind of crawl
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub scan_type: Option<ScanType>,
+    /// Cron expression, e.g. `0 2 * * *`
+    pub schedule: String,
+    /// Fields not covered by the typed model
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A Starfish scan, as returned by `GET /scan/{id}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scan {
+    /// Scan id, unique across volumes
+    pub id: String,
+    /// Name of the scanned volume
+    pub volume: String,
+    /// Kind of crawl
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub scan_type: Option<ScanType>,
+    /// Current lifecycle state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<ScanState>,
+    /// User who started the scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_by: Option<String>,
+    /// Scanned path relative to the volume root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crawler_path: Option<String>,
+    /// When the crawl started, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<f64>,
+    /// When the crawl ended, in seconds since the Unix epoch; unset while it runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<f64>,
+    /// Files processed so far
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_files: Option<u64>,
+    /// Directories processed so far
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_dirs: Option<u64>,
+    /// Entries the crawler failed to read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_errors: Option<u64>,
+    /// Fields not covered by the typed model
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+    /// Payload the model was read from, as sent by the server
+    #[serde(skip)]
+    pub payload: Option<Value>,
+}
+
+impl Scan {
+    /// Files and directories processed so far.
+    pub fn entries(&self) -> u64 {
+        self.num_files.unwrap_or(0) + self.num_dirs.unwrap_or(0)
+    }
+
+    /// Time spent crawling: up to the end time, or up to `now` while the scan runs.
+    pub fn elapsed(&self, now: f64) -> Option<Duration> {
+        let start = self.start_time?;
+        let end = self.end_time.unwrap_or(now);
+        Some(Duration::from_secs_f64((end - start).max(0.0)))
+    }
+
+    /// Entries processed per second over the whole crawl.
+    pub fn throughput(&self, now: f64) -> Option<f64> {
+        let secs = self.elapsed(now)?.as_secs_f64();
+        (secs > 0.0).then(|| self.entries() as f64 / secs)
+    }
+}
+
+/// Envelope of a page of `GET /scan/`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanList {
+    /// Scans on this page
+    pub scans: Vec<Scan>,
+    /// Cursor for the next page, on servers that page with cursors rather than offsets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Fields not covered by the typed model
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A file, directory or link from the metadata index, as returned by `GET /query/`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Name of the volume holding the entry
+    pub volume: String,
+    /// Path of the containing directory, relative to the volume root
+    #[serde(default)]
+    pub parent_path: String,
+    /// Name of the entry itself
+    #[serde(rename = "fn")]
+    pub name: String,
+    /// Kind of entry
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub entry_type: Option<EntryType>,
+    /// Size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Owner user id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    /// Owner group id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// Owner user name, if the agent could resolve it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Owner group name, if the agent could resolve it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groupname: Option<String>,
+    /// Permission bits, e.g. `0o644`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Modification time, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<i64>,
+    /// Access time, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub atime: Option<i64>,
+    /// Status change time, in seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ctime: Option<i64>,
+    /// Starfish tags attached to the entry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Fields not covered by the typed model
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+    /// Payload the model was read from, as sent by the server
+    #[serde(skip)]
+    pub payload: Option<Value>,
+}
+
+impl Entry {
+    /// Path of the entry relative to the volume root, without a leading slash.
+    pub fn path(&self) -> String {
+        let parent = self.parent_path.trim_matches('/');
+        if parent.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}/{}", parent, self.name)
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.entry_type == Some(EntryType::Directory)
+    }
+}
+
+/// Entries of one group from an aggregated query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryGroup {
+    /// Value shared by the entries, e.g. a uid or an extension
+    pub group: Value,
+    /// Number of entries
+    pub count: u64,
+    /// Total size in bytes
+    pub size: u64,
+    /// Fields not covered by the typed model
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A path inside a volume, written `volume:path` on the command line and in the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumePath {
+    /// Volume name
+    pub volume: String,
+    /// Path relative to the volume root, without leading or trailing slashes; empty for
+    /// the root itself
+    pub path: String,
+}
+
+impl FromStr for VolumePath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (volume, path) = s.split_once(':').unwrap_or((s, ""));
+        if volume.is_empty() {
+            return Err(format!("Missing volume name in '{}', expected VOLUME[:PATH]", s));
+        }
+        Ok(Self {
+            volume: volume.to_string(),
+            path: path.trim_matches('/').to_string(),
+        })
+    }
+}
+
+impl fmt::Display for VolumePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.volume, self.path)
+    }
+}
+
+/// Crawler settings for a new scan; unset fields use the volume defaults.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CrawlerOptions {
+    /// Number of crawler threads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<u32>,
+    /// How many directory levels below the scanned path to descend
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<u32>,
+    /// Agent to run the crawler on instead of the volume default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_address: Option<String>,
+}
+
+/// Body of `POST /scan/`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StartScanRequest {
+    /// Volume and path to scan, as `volume:path`
+    #[serde(serialize_with = "serialize_display")]
+    pub volume: VolumePath,
+    /// Kind of crawl
+    #[serde(rename = "type")]
+    pub scan_type: ScanType,
+    /// Crawler settings
+    pub crawler_options: CrawlerOptions,
+}
+
+fn serialize_display<T: fmt::Display, S: serde::Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
@@ -0,0 +1,613 @@
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
+use rsf::models::{Entry, QueryGroup, Scan, Volume};
+use serde::Serialize;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::pin::pin;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Columns never shrink below this width when fitting a table to the terminal
+const MIN_COLUMN_WIDTH: usize = 6;
+const COLUMN_SEPARATOR: &str = "  ";
+const ELLIPSIS: char = '…';
+/// Most items printed at once when streaming a list
+const STREAM_BATCH_SIZE: usize = 1000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns for humans
+    #[default]
+    Table,
+    /// Pretty-printed JSON
+    Json,
+    /// YAML document
+    Yaml,
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON document per line
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// Parses a format name from the config file or environment.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match Self::from_str(name, true) {
+            Ok(format) => Ok(format),
+            Err(_) => bail!(
+                "Unknown output format '{}' (expected one of: table, json, yaml, csv, ndjson)",
+                name
+            ),
+        }
+    }
+}
+
+/// Types that can be listed by the shared renderer.
+pub trait Tabular: Serialize {
+    /// Fields of the typed model, offered when a column is misspelled
+    const FIELDS: &'static [&'static str];
+    /// Fields shown in tables and CSV unless `--columns` picks others
+    const DEFAULT_COLUMNS: &'static [&'static str];
+
+    /// Payload the item was read from, printed instead of the typed model so output
+    /// shows exactly what the server sent
+    fn payload(&self) -> Option<&Value> {
+        None
+    }
+}
+
+impl Tabular for Volume {
+    const FIELDS: &'static [&'static str] = &[
+        "vol",
+        "id",
+        "type",
+        "default_agent_address",
+        "roots",
+        "mount_opts",
+        "ignored_dirs",
+        "scheduled_scans",
+        "total_size",
+        "num_files",
+        "num_dirs",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &["vol", "type", "default_agent_address"];
+
+    fn payload(&self) -> Option<&Value> {
+        self.payload.as_ref()
+    }
+}
+
+impl Tabular for Scan {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "volume",
+        "type",
+        "state",
+        "requested_by",
+        "crawler_path",
+        "start_time",
+        "end_time",
+        "num_files",
+        "num_dirs",
+        "num_errors",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] =
+        &["id", "volume", "type", "state", "requested_by"];
+
+    fn payload(&self) -> Option<&Value> {
+        self.payload.as_ref()
+    }
+}
+
+impl Tabular for Entry {
+    const FIELDS: &'static [&'static str] = &[
+        "volume",
+        "parent_path",
+        "fn",
+        "type",
+        "size",
+        "uid",
+        "gid",
+        "username",
+        "groupname",
+        "mode",
+        "mtime",
+        "atime",
+        "ctime",
+        "tags",
+    ];
+    const DEFAULT_COLUMNS: &'static [&'static str] =
+        &["parent_path", "fn", "type", "size", "username", "mtime"];
+
+    fn payload(&self) -> Option<&Value> {
+        self.payload.as_ref()
+    }
+}
+
+impl Tabular for QueryGroup {
+    const FIELDS: &'static [&'static str] = &["group", "count", "size"];
+    const DEFAULT_COLUMNS: &'static [&'static str] = &["group", "count", "size"];
+}
+
+/// Options shared by list commands to shape their output.
+#[derive(Args, Default)]
+pub struct ListOptions {
+    /// Comma-separated fields to show in table and CSV output; nested fields use a dot path
+    #[arg(long, value_delimiter = ',', value_name = "FIELDS")]
+    columns: Option<Vec<String>>,
+    /// Print each item with a template such as '{{vol}}\t{{type}}' instead of --output
+    #[arg(long, conflicts_with = "columns")]
+    template: Option<String>,
+}
+
+impl ListOptions {
+    /// Shows `columns` in table and CSV output unless `--columns` picks others.
+    pub fn or_columns(mut self, columns: &[&str]) -> Self {
+        self.columns
+            .get_or_insert_with(|| columns.iter().map(|c| c.to_string()).collect());
+        self
+    }
+}
+
+/// Prints command results in the format picked with `--output`.
+pub struct Renderer {
+    format: OutputFormat,
+}
+
+impl Renderer {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// Prints a list of items.
+    pub fn list<T: Tabular>(&self, items: &[T], options: &ListOptions) -> Result<()> {
+        let mut printer = ListPrinter::new(self.format, options);
+        printer.print::<T>(items)?;
+        printer.finish::<T>()
+    }
+
+    /// Prints items as they arrive, so long listings show up before they are complete.
+    ///
+    /// Tables take their column widths from the first batch of items; later values that
+    /// do not fit are truncated.
+    pub async fn list_stream<T: Tabular>(
+        &self,
+        items: impl Stream<Item = Result<T>>,
+        options: &ListOptions,
+    ) -> Result<()> {
+        let mut batches = pin!(items.ready_chunks(STREAM_BATCH_SIZE));
+        let mut printer = ListPrinter::new(self.format, options);
+        while let Some(batch) = batches.next().await {
+            let batch = batch.into_iter().collect::<Result<Vec<T>>>()?;
+            printer.print::<T>(&batch)?;
+        }
+        printer.finish::<T>()
+    }
+
+    /// Prints a single item; JSON and YAML print the object itself rather than a list.
+    pub fn item<T: Tabular>(&self, item: &T) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => {
+                writeln!(std::io::stdout(), "{}", serde_json::to_string_pretty(&to_value(item)?)?)?
+            }
+            OutputFormat::Yaml => {
+                write!(std::io::stdout(), "{}", serde_yaml::to_string(&to_value(item)?)?)?
+            }
+            _ => self.list(std::slice::from_ref(item), &ListOptions::default())?,
+        }
+        Ok(())
+    }
+}
+
+/// Sectioned `Label: value` summary of a single item, shown by `show` commands in
+/// table format.
+#[derive(Default)]
+pub struct Report {
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Report {
+    /// Starts a new section; fields added after it are listed under `title`.
+    pub fn section(mut self, title: impl Into<String>) -> Self {
+        self.sections.push((title.into(), Vec::new()));
+        self
+    }
+
+    /// Adds a `label: value` line; an empty label prints the value on its own.
+    pub fn field(mut self, label: &str, value: impl fmt::Display) -> Self {
+        if self.sections.is_empty() {
+            self.sections.push((String::new(), Vec::new()));
+        }
+        if let Some((_, fields)) = self.sections.last_mut() {
+            fields.push((label.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// Adds a field only when there is a value for it.
+    pub fn optional(self, label: &str, value: Option<impl fmt::Display>) -> Self {
+        match value {
+            Some(value) => self.field(label, value),
+            None => self,
+        }
+    }
+
+    pub fn print(&self) -> Result<()> {
+        let width = self
+            .sections
+            .iter()
+            .flat_map(|(_, fields)| fields)
+            .filter(|(label, _)| !label.is_empty())
+            .map(|(label, _)| label.width() + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut out = std::io::stdout().lock();
+        let sections = self.sections.iter().filter(|(_, fields)| !fields.is_empty());
+        for (i, (title, fields)) in sections.enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            if !title.is_empty() {
+                writeln!(out, "{}", title)?;
+            }
+            for (label, value) in fields {
+                if label.is_empty() {
+                    writeln!(out, "  {}", value)?;
+                    continue;
+                }
+                let label = format!("{}:", label);
+                writeln!(out, "  {}{}  {}", label, " ".repeat(width - label.width()), value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prints list items in batches, keeping what the format needs between them.
+struct ListPrinter<'a> {
+    format: OutputFormat,
+    options: &'a ListOptions,
+    template: Option<Template>,
+    /// Table and CSV columns, known once the first batch is checked
+    columns: Option<Vec<&'a str>>,
+    widths: Vec<usize>,
+    printed: usize,
+}
+
+impl<'a> ListPrinter<'a> {
+    fn new(format: OutputFormat, options: &'a ListOptions) -> Self {
+        Self {
+            format,
+            options,
+            template: options.template.as_deref().map(Template::parse),
+            columns: None,
+            widths: Vec::new(),
+            printed: 0,
+        }
+    }
+
+    fn print<T: Tabular>(&mut self, items: &[T]) -> Result<()> {
+        let mut out = std::io::stdout().lock();
+        let first = self.printed == 0;
+        self.printed += items.len();
+
+        if let Some(template) = &self.template {
+            let values = to_values(items)?;
+            if first {
+                check_columns::<T>(template.fields(), &values)?;
+            }
+            for value in &values {
+                writeln!(out, "{}", template.render(value))?;
+            }
+            return Ok(out.flush()?);
+        }
+
+        match self.format {
+            OutputFormat::Json => {
+                for (i, item) in items.iter().enumerate() {
+                    let separator = if first && i == 0 { "[\n" } else { ",\n" };
+                    let json = serde_json::to_string_pretty(&to_value(item)?)?;
+                    write!(out, "{}  {}", separator, json.replace('\n', "\n  "))?;
+                }
+            }
+            OutputFormat::Yaml => {
+                for item in items {
+                    write!(out, "{}", serde_yaml::to_string(&[to_value(item)?])?)?;
+                }
+            }
+            OutputFormat::Ndjson => {
+                for item in items {
+                    writeln!(out, "{}", serde_json::to_string(&to_value(item)?)?)?;
+                }
+            }
+            OutputFormat::Csv | OutputFormat::Table => {
+                let values = to_values(items)?;
+                let columns = match &self.columns {
+                    Some(columns) => columns,
+                    None => {
+                        let columns: Vec<&str> = match &self.options.columns {
+                            Some(columns) => columns.iter().map(String::as_str).collect(),
+                            None => T::DEFAULT_COLUMNS.to_vec(),
+                        };
+                        check_columns::<T>(columns.iter().copied(), &values)?;
+                        self.columns.insert(columns)
+                    }
+                };
+                let rows: Vec<Vec<String>> = values.iter().map(|v| row(v, columns)).collect();
+                if self.format == OutputFormat::Csv {
+                    write_csv(&mut out, first.then_some(columns.as_slice()), &rows)?;
+                } else {
+                    if first {
+                        let headers: Vec<String> =
+                            columns.iter().map(|c| c.to_uppercase()).collect();
+                        self.widths = table_widths(&headers, &rows);
+                        write_table_row(&mut out, &headers, &self.widths)?;
+                    }
+                    for row in &rows {
+                        write_table_row(&mut out, row, &self.widths)?;
+                    }
+                }
+            }
+        }
+        Ok(out.flush()?)
+    }
+
+    /// Closes the list once all items are printed.
+    fn finish<T: Tabular>(mut self) -> Result<()> {
+        if self.template.is_some() {
+            return Ok(());
+        }
+        match self.format {
+            // Empty tables and CSV still get their header
+            OutputFormat::Csv | OutputFormat::Table if self.printed == 0 => self.print::<T>(&[])?,
+            OutputFormat::Json if self.printed == 0 => writeln!(std::io::stdout(), "[]")?,
+            OutputFormat::Json => writeln!(std::io::stdout(), "\n]")?,
+            OutputFormat::Yaml if self.printed == 0 => writeln!(std::io::stdout(), "[]")?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Value printed for an item: its payload if it kept one, else the typed model.
+pub fn to_value<T: Tabular>(item: &T) -> Result<Value> {
+    match item.payload() {
+        Some(payload) => Ok(payload.clone()),
+        None => Ok(serde_json::to_value(item)?),
+    }
+}
+
+fn to_values<T: Tabular>(items: &[T]) -> Result<Vec<Value>> {
+    items.iter().map(to_value).collect()
+}
+
+fn row(value: &Value, columns: &[&str]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| cell(lookup(value, column).unwrap_or(&Value::Null)))
+        .collect()
+}
+
+/// Follows a dot path such as `roots.agent1` or `tags.0` into a value.
+pub fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// Rejects fields that are neither a field of the typed model nor present in the data.
+pub fn check_columns<'a, T: Tabular>(
+    columns: impl IntoIterator<Item = &'a str>,
+    values: &[Value],
+) -> Result<()> {
+    for column in columns {
+        let root = column.split('.').next().unwrap_or(column);
+        let known = T::FIELDS.contains(&root)
+            || values.iter().any(|value| value.get(root).is_some());
+        if !known {
+            bail!(
+                "Unknown field '{}' (available: {})",
+                column,
+                T::FIELDS.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Text with `{{field.path}}` placeholders, rendered once per item.
+struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+enum TemplatePart {
+    Text(String),
+    Field(String),
+}
+
+impl Template {
+    fn parse(template: &str) -> Self {
+        // Shells pass '\t' through literally, so accept the usual escapes
+        let template = template.replace("\\t", "\t").replace("\\n", "\n");
+        let mut parts = Vec::new();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_string()));
+            }
+            let field = rest[start + 2..start + end].trim();
+            parts.push(TemplatePart::Field(field.to_string()));
+            rest = &rest[start + end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_string()));
+        }
+        Self { parts }
+    }
+
+    fn fields(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            TemplatePart::Field(field) => Some(field.as_str()),
+            TemplatePart::Text(_) => None,
+        })
+    }
+
+    fn render(&self, value: &Value) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Text(text) => text.clone(),
+                TemplatePart::Field(field) => cell(lookup(value, field).unwrap_or(&Value::Null)),
+            })
+            .collect()
+    }
+}
+
+/// Text for a single value: strings unquoted, missing values empty, nested values as JSON.
+pub fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+/// Writes CSV rows, preceded by the header row if there is one.
+fn write_csv(out: &mut impl Write, header: Option<&[&str]>, rows: &[Vec<String>]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    if let Some(header) = header {
+        writer.write_record(header)?;
+    }
+    for row in rows {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Column widths that fit the headers and rows, shrunk to the terminal width.
+fn table_widths(headers: &[String], rows: &[Vec<String>]) -> Vec<usize> {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.width()).collect();
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.width());
+        }
+    }
+    if let Some(max_width) = terminal_width() {
+        fit_widths(&mut widths, max_width);
+    }
+    widths
+}
+
+fn write_table_row(out: &mut impl Write, values: &[String], widths: &[usize]) -> Result<()> {
+    let cells: Vec<String> = values
+        .iter()
+        .zip(widths)
+        .map(|(value, &width)| {
+            // Tables are one line per row, whatever the value contains
+            let value = value.replace(['\n', '\t'], " ");
+            let value = truncate(&value, width);
+            let padding = width.saturating_sub(value.width());
+            format!("{}{}", value, " ".repeat(padding))
+        })
+        .collect();
+    writeln!(out, "{}", cells.join(COLUMN_SEPARATOR).trim_end())?;
+    Ok(())
+}
+
+/// Width of the terminal, or `None` when stdout is not one.
+pub fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    // Some pseudo terminals report a zero size
+    crossterm::terminal::size()
+        .ok()
+        .map(|(cols, _)| cols as usize)
+        .filter(|&cols| cols > 0)
+}
+
+/// Shrinks the widest columns until the table fits into `max_width`.
+fn fit_widths(widths: &mut [usize], max_width: usize) {
+    let separators = COLUMN_SEPARATOR.len() * widths.len().saturating_sub(1);
+    while widths.iter().sum::<usize>() + separators > max_width {
+        let Some(widest) = widths
+            .iter_mut()
+            .filter(|w| **w > MIN_COLUMN_WIDTH)
+            .max_by_key(|w| **w)
+        else {
+            break;
+        };
+        *widest -= 1;
+    }
+}
+
+/// Cuts `value` to at most `width` display columns, marking the cut with an ellipsis.
+pub fn truncate(value: &str, width: usize) -> String {
+    if value.width() <= width {
+        return value.to_string();
+    }
+    let mut result = String::new();
+    let mut used = 0;
+    for c in value.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        result.push(c);
+        used += w;
+    }
+    result.push(ELLIPSIS);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, value: &Value) -> String {
+        Template::parse(template).render(value)
+    }
+
+    #[test]
+    fn lookup_follows_dot_paths() {
+        let value = json!({"vol": "proj", "roots": {"agent1": "/mnt"}, "tags": ["a", "b"]});
+        assert_eq!(lookup(&value, "vol"), Some(&json!("proj")));
+        assert_eq!(lookup(&value, "roots.agent1"), Some(&json!("/mnt")));
+        assert_eq!(lookup(&value, "tags.1"), Some(&json!("b")));
+        assert_eq!(lookup(&value, "tags.2"), None);
+        assert_eq!(lookup(&value, "tags.first"), None);
+        assert_eq!(lookup(&value, "vol.name"), None);
+        assert_eq!(lookup(&value, "missing"), None);
+    }
+
+    #[test]
+    fn templates_fill_in_fields() {
+        let value = json!({"vol": "proj", "roots": {"agent1": "/mnt"}, "size": 10});
+        let template = Template::parse("{{vol}}: {{ roots.agent1 }} ({{size}} bytes)");
+        assert_eq!(template.fields().collect::<Vec<_>>(), ["vol", "roots.agent1", "size"]);
+        assert_eq!(template.render(&value), "proj: /mnt (10 bytes)");
+        assert_eq!(render("{{vol}}\\t{{missing}}\\n", &value), "proj\t\n");
+    }
+
+    #[test]
+    fn unclosed_placeholders_are_text() {
+        let value = json!({"vol": "proj"});
+        assert_eq!(render("{{vol}} {{vol", &value), "proj {{vol");
+        assert_eq!(Template::parse("{{vol").fields().count(), 0);
+        assert_eq!(render("vol}} {{vol}}", &value), "vol}} proj");
+    }
+}